@@ -1,6 +1,8 @@
 #!/bin/zsh
 
 set -u
+# commr compares bytes, as comm does in the C locale
+export LC_ALL=C
 IN_DIR="tests/inputs"
 OUT_DIR="tests/expected"
 
@@ -45,4 +47,76 @@ comm -3      $IN_DIR/file1.txt $IN_DIR/file2.txt | sed "s/\t/:/g" > $OUT_DIR/fil
 comm -12     $IN_DIR/file1.txt $IN_DIR/file2.txt | sed "s/\t/:/g" > $OUT_DIR/file1_file2.12.delim.out
 comm -23     $IN_DIR/file1.txt $IN_DIR/file2.txt | sed "s/\t/:/g" > $OUT_DIR/file1_file2.23.delim.out
 comm -13     $IN_DIR/file1.txt $IN_DIR/file2.txt | sed "s/\t/:/g" > $OUT_DIR/file1_file2.13.delim.out
-comm -123    $IN_DIR/file1.txt $IN_DIR/file2.txt | sed "s/\t/:/g" > $OUT_DIR/file1_file2.123.delim.out
\ No newline at end of file
+comm -123    $IN_DIR/file1.txt $IN_DIR/file2.txt | sed "s/\t/:/g" > $OUT_DIR/file1_file2.123.delim.out
+comm                 $IN_DIR/unsorted.txt $IN_DIR/file2.txt > $OUT_DIR/unsorted_file2.out
+comm --check-order   $IN_DIR/unsorted.txt $IN_DIR/file2.txt > $OUT_DIR/unsorted_file2.check-order.out
+comm --nocheck-order $IN_DIR/unsorted.txt $IN_DIR/file2.txt > $OUT_DIR/unsorted_file2.nocheck-order.out
+comm --check-order   $IN_DIR/file1.txt $IN_DIR/file1.txt > $OUT_DIR/file1_file1.check-order.out
+comm                 $IN_DIR/unsorted.txt $IN_DIR/unsorted.txt > $OUT_DIR/unsorted_unsorted.out
+
+# comm has no -n or -V: sort compares what comm paired, ignoring the tabs
+comm <(sort $IN_DIR/numbers1.txt) <(sort $IN_DIR/numbers2.txt) | sort -n > $OUT_DIR/numbers1_numbers2.n.out
+comm <(sort $IN_DIR/versions1.txt) <(sort $IN_DIR/versions2.txt) | sort -b -V > $OUT_DIR/versions1_versions2.V.out
+
+comm --total    $IN_DIR/file1.txt $IN_DIR/file2.txt > $OUT_DIR/file1_file2.total.out
+comm --total -3 $IN_DIR/file1.txt $IN_DIR/file2.txt | sed "s/\t/:/g" > $OUT_DIR/file1_file2.3.total.delim.out
+
+# GNU comm has no -i; lines print as they are in the files
+cat > $OUT_DIR/upper_file2.i.out <<'END'
+A
+		B
+		C
+END
+
+# comm has no fields; rows are matched on the key column
+cat > $OUT_DIR/stock1_stock2.t.out <<'END'
+		apple,3,red
+		banana,12,yellow
+		"cherry, sour",40,red
+	date,9,brown
+kiwi,7,green
+END
+
+cat > $OUT_DIR/stock1_stock2.t.changed.total.out <<'END'
+		apple,3,red
+			banana,12,yellow	banana,10,yellow
+		"cherry, sour",40,red
+	date,9,brown
+kiwi,7,green
+1	1	2	1	total
+END
+
+cat > $OUT_DIR/hosts1_hosts2.t.k2.changed.delim.out <<'END'
+|||web1	10.0.0.1|web-a	10.0.0.1
+web2	10.0.0.2
+|cache	10.0.0.3
+||db1	10.0.0.5
+END
+
+# Lines need not be UTF-8, and -z ends them with NUL
+comm         $IN_DIR/latin1.txt $IN_DIR/latin2.txt > $OUT_DIR/latin1_latin2.out
+comm -z      $IN_DIR/nul1.txt $IN_DIR/nul2.txt > $OUT_DIR/nul1_nul2.z.out
+comm -12 -z  $IN_DIR/nul1.txt $IN_DIR/nul2.txt > $OUT_DIR/nul1_nul2.z.12.out
+
+# --sort sorts the files first
+comm         <(sort $IN_DIR/shuffled1.txt) <(sort $IN_DIR/shuffled2.txt) > $OUT_DIR/shuffled1_shuffled2.sort.out
+comm -3      <(sort $IN_DIR/shuffled1.txt) <(sort $IN_DIR/shuffled2.txt) > $OUT_DIR/shuffled1_shuffled2.sort.3.out
+comm         <(sort -z $IN_DIR/nul2.txt) <(sort -z $IN_DIR/nul1.txt) -z > $OUT_DIR/nul2_nul1.sort.z.out
+
+# comm only takes two files
+cat > $OUT_DIR/hosts.abc.out <<'END'
+010	cache1
+111	db1
+101	web1
+110	web2
+001	web3
+END
+
+cat > $OUT_DIR/hosts.abc.m.delim.out <<'END'
+tests/inputs/hosts.a.txt,tests/inputs/hosts.b.txt,tests/inputs/hosts.c.txt,line
+0,1,0,cache1
+1,1,1,db1
+1,0,1,web1
+1,1,0,web2
+0,0,1,web3
+END
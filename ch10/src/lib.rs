@@ -1,145 +1,706 @@
 use std::{
     error::Error,
     fs::File,
-    io::{self, BufRead, BufReader},
-    cmp::Ordering::*,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    cmp::{Ordering::{self, *}, Reverse},
+    collections::BinaryHeap,
+    iter, mem,
 };
 use clap::{Arg, ArgAction, Command};
+use csv::ByteRecord;
+use csv_core::{ReadRecordResult, ReaderBuilder};
+use tempfile::{NamedTempFile, TempPath};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
 pub struct Config {
-    file1: String,
-    file2: String,
+    files: Vec<String>,
     show_col1: bool,
     show_col2: bool,
     show_col3: bool,
-    insensitive: bool,
+    collation: Collation,
     delimiter: String,
+    check_order: CheckOrder,
+    total: bool,
+    fields: Option<Fields>,
+    changed: bool,
+    matrix: bool,
+    zero_terminated: bool,
+    sort: bool,
+    // How much of each file --sort holds in memory
+    buffer_size: usize,
 }
 
+// How lines split into fields with -t, to be compared on the -k field
+#[derive(Debug)]
+pub struct Fields {
+    delimiter: u8,
+    key: usize,
+}
+
+// How lines are ordered, the same as by sort with no options, -f, -n or
+// -V in the C locale
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collation {
+    Bytes,
+    Fold,
+    Numeric,
+    Version,
+}
+
+// Whether unsorted input is an error, a warning or ignored. By default,
+// like GNU comm, only a file with lines not in the other is checked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckOrder {
+    Default,
+    Enabled,
+    Disabled,
+}
+
+// The records of each file are sorted for --sort in chunks of about this
+// size, counting this much for each record besides its bytes
+const RECORD_OVERHEAD: usize = 64;
+
+// Sorted chunks are merged this many at a time, as GNU sort does, so that
+// few files are open at once
+const MERGE_FAN_IN: usize = 16;
+
 enum Column<'a> {
-    Col1(&'a str),
-    Col2(&'a str),
-    Col3(&'a str),
+    Col1(&'a [u8]),
+    Col2(&'a [u8]),
+    Col3(&'a [u8]),
+    // Lines with the same key but other fields changed, from each file
+    Col4(&'a [u8], &'a [u8]),
+}
+
+// A line of input, and its fields with -t
+struct Line {
+    text: Vec<u8>,
+    fields: Option<ByteRecord>,
+}
+
+impl Line {
+    // About how much memory the line takes, its fields included
+    fn size(&self) -> usize {
+        let fields = self.fields.as_ref()
+            .map_or(0, |record| record.as_slice().len() + record.len() * mem::size_of::<usize>());
+        self.text.len() + fields + RECORD_OVERHEAD
+    }
+}
+
+impl Config {
+    // What ends each line of input and output
+    fn terminator(&self) -> u8 {
+        if self.zero_terminated { b'\0' } else { b'\n' }
+    }
+
+    // The lines of an input from its records, split into fields with -t
+    // by one parser for all of them
+    fn lines<'a>(&self, records: impl Iterator<Item = io::Result<Vec<u8>>> + 'a) -> Lines<'a> {
+        let mut split = self.fields.as_ref().map(Split::new);
+        Box::new(records.map(move |text| {
+            let text = text?;
+            let fields = split.as_mut().map(|split| split.record(&text));
+            Ok(Line { text, fields })
+        }))
+    }
+
+    // What lines are compared on: the whole line, or its key field. A line
+    // without the field has an empty key.
+    fn key<'a>(&self, line: &'a Line) -> &'a [u8] {
+        match (&self.fields, &line.fields) {
+            (Some(fields), Some(record)) => record.get(fields.key).unwrap_or_default(),
+            _ => &line.text,
+        }
+    }
+
+    fn compare(&self, line1: &Line, line2: &Line) -> Ordering {
+        compare(self.collation, self.key(line1), self.key(line2))
+    }
+
+    fn head(&self, line: &Line, input: usize) -> Reverse<Head> {
+        Reverse(Head { key: self.key(line).to_vec(), collation: self.collation, input })
+    }
+}
+
+// The current line of an input in the merge, the least key first and
+// then the first input
+struct Head {
+    key: Vec<u8>,
+    collation: Collation,
+    input: usize,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Head) -> Ordering {
+        compare(self.collation, &self.key, &other.key).then(self.input.cmp(&other.input))
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Head) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Head) -> bool {
+        self.cmp(other) == Equal
+    }
+}
+
+impl Eq for Head {}
+
+type Lines<'a> = Box<dyn Iterator<Item = io::Result<Line>> + 'a>;
+
+// One input file, read a line at a time
+struct Input<'a> {
+    filename: &'a str,
+    lines: Lines<'a>,
+    line: Option<Line>,
+    line_number: usize,
+    // Whether a line was found out of order
+    unsorted: bool,
+}
+
+// Splits lines into fields with -t, as CSV with the delimiter given. The
+// parser and its buffers are reused from one line to the next.
+struct Split {
+    reader: csv_core::Reader,
+    output: Vec<u8>,
+    ends: Vec<usize>,
+}
+
+impl Split {
+    fn new(fields: &Fields) -> Split {
+        Split {
+            reader: ReaderBuilder::new().delimiter(fields.delimiter).build(),
+            output: vec![0; 1024],
+            ends: vec![0; 64],
+        }
+    }
+
+    // The fields of the first record in `text`, the parser taking any
+    // bytes as CSV; an empty line has no fields
+    fn record(&mut self, text: &[u8]) -> ByteRecord {
+        self.reader.reset();
+        let mut input = text;
+        let (mut nout, mut nend) = (0, 0);
+        loop {
+            let (result, nin, out, end) =
+                self.reader.read_record(input, &mut self.output[nout..], &mut self.ends[nend..]);
+            input = &input[nin..];
+            nout += out;
+            nend += end;
+            match result {
+                ReadRecordResult::InputEmpty => {}
+                ReadRecordResult::OutputFull => self.output.resize(2 * self.output.len(), 0),
+                ReadRecordResult::OutputEndsFull => self.ends.resize(2 * self.ends.len(), 0),
+                ReadRecordResult::Record | ReadRecordResult::End => break,
+            }
+        }
+        let mut record = ByteRecord::with_capacity(nout, nend);
+        let mut start = 0;
+        for &end in &self.ends[..nend] {
+            record.push_field(&self.output[start..end]);
+            start = end;
+        }
+        record
+    }
+}
+
+impl<'a> Input<'a> {
+    fn new(filename: &'a str, lines: Lines<'a>) -> MyResult<Input<'a>> {
+        let mut input = Input {
+            filename,
+            lines,
+            line: None,
+            line_number: 0,
+            unsorted: false,
+        };
+        input.line = input.next()?;
+        input.line_number = 1;
+        Ok(input)
+    }
+
+    fn next(&mut self) -> MyResult<Option<Line>> {
+        Ok(self.lines.next().transpose().map_err(|e| format!("{}: {}", self.filename, e))?)
+    }
+
+    // Moves on to the next line, telling if it sorts before the current one
+    fn advance(&mut self, config: &Config) -> MyResult<bool> {
+        let next = self.next()?;
+        self.line_number += 1;
+        let unsorted = matches!(
+            (&self.line, &next),
+            (Some(prev), Some(next)) if config.compare(prev, next) == Greater
+        );
+        self.line = next;
+        Ok(unsorted)
+    }
 }
 
 pub fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+        _ => Ok(Box::new(BufReader::new(
+            File::open(filename).map_err(|e| format!("{}: {}", filename, e))?,
+        ))),
     }
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let file1 = &config.file1;
-    let file2 = &config.file2;
+// Reads the records in `file` that end with `terminator`, as bytes and
+// without it
+fn records<'a>(mut file: impl BufRead + 'a, terminator: u8) -> impl Iterator<Item = io::Result<Vec<u8>>> + 'a {
+    iter::from_fn(move || {
+        let mut record = vec![];
+        match file.read_until(terminator, &mut record) {
+            Ok(0) => None,
+            Ok(_) => {
+                if record.last() == Some(&terminator) {
+                    record.pop();
+                }
+                Some(Ok(record))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    })
+}
+
+// Sorts lines for --sort. Those that fit in the buffer are sorted in
+// memory, and more are sorted in chunks written to temporary files and
+// then merged, in more than one pass if there are too many to open.
+fn sort_lines<'a>(config: &'a Config, lines: Lines<'a>) -> io::Result<Lines<'a>> {
+    let mut runs = vec![];
+    let mut chunk = vec![];
+    let mut size = 0;
+    for line in lines {
+        let line = line?;
+        size += line.size();
+        chunk.push(line);
+        if size >= config.buffer_size {
+            chunk.sort_by(|a, b| config.compare(a, b));
+            runs.push(write_run(config, chunk.drain(..).map(Ok))?);
+            size = 0;
+        }
+    }
+    chunk.sort_by(|a, b| config.compare(a, b));
+    if runs.is_empty() {
+        return Ok(Box::new(chunk.into_iter().map(Ok)));
+    }
+    if !chunk.is_empty() {
+        runs.push(write_run(config, chunk.into_iter().map(Ok))?);
+    }
 
-    if file1 == "-" && file2 == "-" {
-        return Err(From::from("Both input files cannot be STDIN (\"-\")"));
+    // Neighboring runs are merged, keeping equal lines in order
+    while runs.len() > MERGE_FAN_IN {
+        let mut merged = vec![];
+        let mut rest = runs.into_iter();
+        loop {
+            let group: Vec<TempPath> = rest.by_ref().take(MERGE_FAN_IN).collect();
+            if group.is_empty() {
+                break;
+            }
+            merged.push(write_run(config, Runs::new(config, group)?)?);
+        }
+        runs = merged;
     }
+    Ok(Box::new(Runs::new(config, runs)?))
+}
 
-    let case = |line: String| {
-        if config.insensitive {
-            line.to_lowercase()
-        } else {
-            line
+// Writes sorted lines to a temporary file, closed until it is merged and
+// removed once its path is dropped
+fn write_run(config: &Config, lines: impl Iterator<Item = io::Result<Line>>) -> io::Result<TempPath> {
+    let mut file = NamedTempFile::new()?;
+    let mut out = BufWriter::new(file.as_file_mut());
+    for line in lines {
+        out.write_all(&line?.text)?;
+        out.write_all(&[config.terminator()])?;
+    }
+    out.flush()?;
+    drop(out);
+    Ok(file.into_temp_path())
+}
+
+// Merges sorted runs of lines, the earlier run first for equal keys so
+// that the sort is stable
+struct Runs<'a> {
+    config: &'a Config,
+    // The files are removed when the merge is dropped
+    paths: Vec<TempPath>,
+    runs: Vec<Lines<'a>>,
+    lines: Vec<Option<Line>>,
+    heap: BinaryHeap<Reverse<Head>>,
+}
+
+impl<'a> Runs<'a> {
+    fn new(config: &'a Config, paths: Vec<TempPath>) -> io::Result<Runs<'a>> {
+        let mut runs = Runs { config, paths: vec![], runs: vec![], lines: vec![], heap: BinaryHeap::new() };
+        for path in paths {
+            let file = BufReader::new(File::open(&path)?);
+            runs.paths.push(path);
+            runs.runs.push(config.lines(records(file, config.terminator())));
+            runs.lines.push(None);
+            runs.read(runs.runs.len() - 1)?;
+        }
+        Ok(runs)
+    }
+
+    fn read(&mut self, run: usize) -> io::Result<()> {
+        if let Some(line) = self.runs[run].next().transpose()? {
+            self.heap.push(self.config.head(&line, run));
+            self.lines[run] = Some(line);
         }
+        Ok(())
+    }
+}
+
+impl Iterator for Runs<'_> {
+    type Item = io::Result<Line>;
+
+    fn next(&mut self) -> Option<io::Result<Line>> {
+        let Reverse(least) = self.heap.pop()?;
+        let line = self.lines[least.input].take()?;
+        Some(self.read(least.input).map(|()| line))
+    }
+}
+
+// Merges the inputs, each sorted, into the lines of all of them, each
+// printed once with the inputs it is in: in the columns of comm for two
+// files, and otherwise as a bitmask or with -m a matrix
+pub fn run(config: Config) -> MyResult<()> {
+    if config.files.iter().filter(|file| *file == "-").count() > 1 {
+        return Err(From::from(if config.files.len() == 2 {
+            "Both input files cannot be STDIN (\"-\")"
+        } else {
+            "Only one input file can be STDIN (\"-\")"
+        }));
+    }
+    let delimiter = config.delimiter.as_bytes();
+    let terminator = config.terminator();
+    let write_row = |out: &mut dyn Write, columns: &[&[u8]]| -> io::Result<()> {
+        out.write_all(&columns.join(delimiter))?;
+        out.write_all(&[terminator])
     };
 
-    let print = |col: Column| {
+    let mut totals = [0; 4];
+    let mut print = |out: &mut dyn Write, col: Column| -> io::Result<()> {
         let mut columns = vec![];
         match col {
             Column::Col1(val) => {
+                totals[0] += 1;
                 if config.show_col1 {
                     columns.push(val);
                 }
             }
             Column::Col2(val) => {
+                totals[1] += 1;
                 if config.show_col2 {
                     if config.show_col1 {
-                        columns.push("");
+                        columns.push(&b""[..]);
                     }
                     columns.push(val);
                 }
             }
             Column::Col3(val) => {
+                totals[2] += 1;
                 if config.show_col3 {
                     if config.show_col1 {
-                        columns.push("");
+                        columns.push(b"");
                     }
                     if config.show_col2 {
-                        columns.push("");
+                        columns.push(b"");
                     }
                     columns.push(val);
                 }
             }
+            Column::Col4(val1, val2) => {
+                totals[3] += 1;
+                for show in [config.show_col1, config.show_col2, config.show_col3] {
+                    if show {
+                        columns.push(b"");
+                    }
+                }
+                columns.push(val1);
+                columns.push(val2);
+            }
         };
+        if columns.is_empty() { Ok(()) } else { write_row(out, &columns) }
+    };
 
-        if !columns.is_empty() {
-            println!("{}", columns.join(&config.delimiter));
+    // Prints a line with the inputs it is in, as "101" for the first and
+    // third of three or in a column for each with -m
+    let print_group = |out: &mut dyn Write, group: &[Option<&Line>]| -> io::Result<()> {
+        let text = group.iter().flatten().next().map_or(&b""[..], |line| &line.text);
+        let columns: Vec<&[u8]> = group.iter().map(|line| if line.is_some() { &b"1"[..] } else { b"0" }).collect();
+        if config.matrix {
+            write_row(out, &[&columns.join(delimiter), text])
+        } else {
+            write_row(out, &[&columns.concat(), text])
         }
     };
 
-    let mut lines1= open(file1)?.lines().filter_map(Result::ok).map(case);
-    let mut lines2= open(file2)?.lines().filter_map(Result::ok).map(case);
-
-    let mut line1 = lines1.next();
-    let mut line2 = lines2.next();
+    let config = &config;
+    let mut inputs = vec![];
+    for filename in &config.files {
+        let lines = config.lines(records(open(filename)?, terminator));
+        let lines = if config.sort {
+            sort_lines(config, lines).map_err(|e| format!("{}: {}", filename, e))?
+        } else {
+            lines
+        };
+        inputs.push(Input::new(filename, lines)?);
+    }
+    let classic = inputs.len() == 2 && !config.matrix;
+    let out = &mut io::stdout().lock();
+    if config.matrix {
+        let header: Vec<&[u8]> = config.files.iter().map(|file| file.as_bytes()).chain([&b"line"[..]]).collect();
+        write_row(out, &header)?;
+    }
 
-    while line1.is_some() || line2.is_some() {
-        match (&line1, &line2) {
+    // Reports each file out of order once, and stops at it with --check-order
+    let mut seen_unpairable = false;
+    let step = |input: &mut Input, file: usize, seen_unpairable: bool| -> MyResult<()> {
+        let check = match config.check_order {
+            CheckOrder::Enabled => true,
+            CheckOrder::Default => seen_unpairable,
+            CheckOrder::Disabled => false,
+        };
+        if input.advance(config)? && check && !input.unsorted {
+            input.unsorted = true;
+            let message = format!(
+                "file {} is not in sorted order: {}:{}",
+                file, input.filename, input.line_number
+            );
+            if config.check_order == CheckOrder::Enabled {
+                return Err(message.into());
+            }
+            eprintln!("{}", message);
+        }
+        Ok(())
+    };
 
-            (Some(val1), Some(val2)) => match val1.cmp(&val2) {
-                Euqal => {
-                    print(Column::Col3(val1));
-                    line1 = lines1.next();
-                    line2 = lines2.next();
-                }
-                Less => {
-                    print(Column::Col1(val1));
-                    line1 = lines1.next();
-                }
-                Greater => {
-                    print(Column::Col2(val2));
-                    line2 = lines2.next();
-                }
-            },
+    let head = |input: &Input, index: usize| input.line.as_ref().map(|line| config.head(line, index));
+    let mut heap: BinaryHeap<Reverse<Head>> = inputs.iter().enumerate()
+        .filter_map(|(index, input)| head(input, index))
+        .collect();
 
-            (Some(val1), None) => {
-                print(Column::Col1(val1));
-                line1 = lines1.next();
+    while let Some(Reverse(least)) = heap.pop() {
+        // Every input with the same key is popped in the order given
+        let mut members = vec![least.input];
+        while let Some(Reverse(next)) = heap.peek() {
+            if compare(config.collation, &next.key, &least.key) != Equal {
+                break;
             }
+            members.push(next.input);
+            heap.pop();
+        }
 
-            (None, Some(val2)) => {
-                print(Column::Col2(val2));
-                line2 = lines2.next();
+        let group: Vec<Option<&Line>> = inputs.iter().enumerate()
+            .map(|(index, input)| input.line.as_ref().filter(|_| members.contains(&index)))
+            .collect();
+        if classic {
+            match (group[0], group[1]) {
+                (Some(val1), Some(val2)) if config.changed && val1.fields != val2.fields => {
+                    print(out, Column::Col4(&val1.text, &val2.text))?;
+                }
+                (Some(val1), Some(_)) => print(out, Column::Col3(&val1.text))?,
+                (Some(val1), None) => print(out, Column::Col1(&val1.text))?,
+                (None, Some(val2)) => print(out, Column::Col2(&val2.text))?,
+                (None, None) => (),
             }
+        } else {
+            print_group(out, &group)?;
+        }
 
-            _ => (),
-        };
+        seen_unpairable |= members.len() < inputs.len();
+        for index in members {
+            step(&mut inputs[index], index + 1, seen_unpairable)?;
+            heap.extend(head(&inputs[index], index));
+        }
+    }
+
+    if config.total {
+        let columns = if config.changed { 4 } else { 3 };
+        let mut summary: Vec<String> = totals[..columns].iter().map(|total| total.to_string()).collect();
+        summary.push("total".to_string());
+        let summary: Vec<&[u8]> = summary.iter().map(|column| column.as_bytes()).collect();
+        write_row(out, &summary)?;
+    }
+    out.flush()?;
+
+    if inputs.iter().any(|input| input.unsorted) {
+        return Err(From::from("input is not in sorted order"));
     }
 
     Ok(())
 }
+
+// Compares lines as sort does under `collation`. Lines that only compare
+// equal as numbers or versions are told apart by their bytes, but -i pairs
+// lines that differ only in case.
+pub fn compare(collation: Collation, a: &[u8], b: &[u8]) -> Ordering {
+    match collation {
+        Collation::Bytes => a.cmp(b),
+        Collation::Fold => a.iter()
+            .map(u8::to_ascii_uppercase)
+            .cmp(b.iter().map(u8::to_ascii_uppercase)),
+        Collation::Numeric => compare_numbers(a, b).then_with(|| a.cmp(b)),
+        Collation::Version => compare_versions(a, b).then_with(|| a.cmp(b)),
+    }
+}
+
+// Splits the number a line starts with, after any blanks, into its sign
+// and its integer and fraction digits without insignificant zeros. A line
+// with no number is zero.
+fn parse_number(line: &[u8]) -> (Ordering, &[u8], &[u8]) {
+    let digits = |text: &[u8]| text.iter().take_while(|c| c.is_ascii_digit()).count();
+    let line = &line[line.iter().take_while(|&&c| c == b' ' || c == b'\t').count()..];
+    let (negative, line) = match line.strip_prefix(b"-") {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let (int, rest) = line.split_at(digits(line));
+    let frac = match rest.strip_prefix(b".") {
+        Some(rest) => &rest[..digits(rest)],
+        None => &[],
+    };
+    let int = &int[int.iter().take_while(|&&c| c == b'0').count()..];
+    let frac = &frac[..frac.len() - frac.iter().rev().take_while(|&&c| c == b'0').count()];
+    let sign = if int.is_empty() && frac.is_empty() {
+        Equal
+    } else if negative {
+        Less
+    } else {
+        Greater
+    };
+    (sign, int, frac)
+}
+
+// Compares the numbers lines start with, like sort -n
+fn compare_numbers(a: &[u8], b: &[u8]) -> Ordering {
+    let (sign_a, int_a, frac_a) = parse_number(a);
+    let (sign_b, int_b, frac_b) = parse_number(b);
+    sign_a.cmp(&sign_b).then_with(|| {
+        let magnitude = int_a.len().cmp(&int_b.len())
+            .then(int_a.cmp(int_b))
+            .then(frac_a.cmp(frac_b));
+        if sign_a == Less { magnitude.reverse() } else { magnitude }
+    })
+}
+
+// Compares lines as versions, like sort -V, with gnulib's filevercmp:
+// "." and ".." first, then other names starting with "."; digits compare
+// as numbers and "~" before anything, even the end; and file suffixes
+// such as ".tar.gz" only count when the rest is equal.
+fn compare_versions(a: &[u8], b: &[u8]) -> Ordering {
+    if a.is_empty() || b.is_empty() {
+        return a.is_empty().cmp(&b.is_empty()).reverse();
+    }
+    if a[0] == b'.' || b[0] == b'.' {
+        let rank = |name: &[u8]| match name {
+            b"." => 0,
+            b".." => 1,
+            [b'.', ..] => 2,
+            _ => 3,
+        };
+        let order = rank(a).cmp(&rank(b));
+        if order != Equal || rank(a) < 2 {
+            return order;
+        }
+    }
+    let (prefix_a, prefix_b) = (&a[..prefix_len(a)], &b[..prefix_len(b)]);
+    let order = compare_version_parts(prefix_a, prefix_b);
+    if order != Equal || (prefix_a.len() == a.len() && prefix_b.len() == b.len()) {
+        order
+    } else {
+        compare_version_parts(a, b)
+    }
+}
+
+// The length of a name without its suffixes, each a "." then a letter or
+// "~" and then letters, digits or "~". As with sort, a hidden name can be
+// all suffix.
+fn prefix_len(name: &[u8]) -> usize {
+    let is_suffix_start = |c: u8| c.is_ascii_alphabetic() || c == b'~';
+    let is_suffix_char = |c: u8| c.is_ascii_alphanumeric() || c == b'~';
+    let mut prefix_len = 0;
+    let mut i = 0;
+    loop {
+        while i + 1 < name.len() && name[i] == b'.' && is_suffix_start(name[i + 1]) {
+            i += 2;
+            while i < name.len() && is_suffix_char(name[i]) {
+                i += 1;
+            }
+        }
+        if i == name.len() {
+            return prefix_len;
+        }
+        i += 1;
+        prefix_len = i;
+    }
+}
+
+fn compare_version_parts(a: &[u8], b: &[u8]) -> Ordering {
+    // Letters sort first, then other characters, but "~" before the end
+    let order = |name: &[u8], i: usize| -> i32 {
+        match name.get(i) {
+            None => -1,
+            Some(c) if c.is_ascii_digit() => 0,
+            Some(c) if c.is_ascii_alphabetic() => i32::from(*c),
+            Some(b'~') => -2,
+            Some(c) => i32::from(*c) + 256,
+        }
+    };
+    let is_digit = |name: &[u8], i: usize| name.get(i).is_some_and(u8::is_ascii_digit);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !is_digit(a, i)) || (j < b.len() && !is_digit(b, j)) {
+            let (order_a, order_b) = (order(a, i), order(b, j));
+            if order_a != order_b {
+                return order_a.cmp(&order_b);
+            }
+            i += 1;
+            j += 1;
+        }
+        while a.get(i) == Some(&b'0') {
+            i += 1;
+        }
+        while b.get(j) == Some(&b'0') {
+            j += 1;
+        }
+        let mut first_diff = Equal;
+        while is_digit(a, i) && is_digit(b, j) {
+            if first_diff == Equal {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if is_digit(a, i) {
+            return Greater;
+        }
+        if is_digit(b, j) {
+            return Less;
+        }
+        if first_diff != Equal {
+            return first_diff;
+        }
+    }
+    Equal
+}
+
 pub fn get_args() -> MyResult<Config> {
     let matches = Command::new("ch10-commr")
         .version("0.1.0")
         .author("Sean Z")
         .about("Rust comm")
         .arg(
-            Arg::new("file1")
-                .value_name("FILE1")
-                .help("Input file 1")
-                .num_args(1)
-                .required(true)
-        )
-        .arg(
-            Arg::new("file2")
-                .value_name("FILE2")
-                .help("Input file 2")
-                .num_args(1)
+            Arg::new("files")
+                .value_name("FILE")
+                .help("Input files, two or more")
+                .num_args(2..)
                 .required(true)
         )
         .arg(
@@ -173,6 +734,94 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Case-insensitive comparison of lines")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("numeric_sort")
+                .short('n')
+                .long("numeric-sort")
+                .help("Compare lines by the numbers they start with, as sorted by sort -n")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("insensitive"),
+        )
+        .arg(
+            Arg::new("version_sort")
+                .short('V')
+                .long("version-sort")
+                .help("Compare lines as version numbers, as sorted by sort -V")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["insensitive", "numeric_sort"]),
+        )
+        .arg(
+            Arg::new("field_delimiter")
+                .short('t')
+                .long("field-separator")
+                .value_name("DELIM")
+                .help("Split lines into CSV fields on DELIM and compare them on a key field"),
+        )
+        .arg(
+            Arg::new("key")
+                .short('k')
+                .long("key")
+                .value_name("FIELD")
+                .help("Compare lines on field FIELD [default: 1]")
+                .value_parser(clap::value_parser!(usize))
+                .requires("field_delimiter"),
+        )
+        .arg(
+            Arg::new("changed")
+                .long("changed")
+                .help("Print lines whose keys match but other fields differ in a fourth column")
+                .action(ArgAction::SetTrue)
+                .requires("field_delimiter"),
+        )
+        .arg(
+            Arg::new("matrix")
+                .short('m')
+                .long("matrix")
+                .help("Print a row of 1s and 0s for the files each line is in, after a header of file names")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("total")
+                .long("total")
+                .help("Output a summary of the number of lines in each column")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("check_order")
+                .long("check-order")
+                .help("Check that the input is correctly sorted, even if all input lines are pairable")
+                .action(ArgAction::SetTrue)
+                .overrides_with("nocheck_order"),
+        )
+        .arg(
+            Arg::new("nocheck_order")
+                .long("nocheck-order")
+                .help("Do not check that the input is correctly sorted")
+                .action(ArgAction::SetTrue)
+                .overrides_with("check_order"),
+        )
+        .arg(
+            Arg::new("zero_terminated")
+                .short('z')
+                .long("zero-terminated")
+                .help("Line delimiter is NUL, not newline")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("sort")
+                .long("sort")
+                .help("Sort each file first, in temporary files if it does not fit in memory")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("buffer_size")
+                .short('S')
+                .long("buffer-size")
+                .value_name("SIZE")
+                .help("Sort in memory up to SIZE of each file, in KiB or with a b, K, M or G suffix")
+                .default_value("64M")
+                .requires("sort"),
+        )
         .arg(
             Arg::new("delimiter")
                 .short('d')
@@ -181,25 +830,153 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Out delimiter")
                 .default_value("\t"),
         )
+        .disable_version_flag(true)
+        .arg(
+            Arg::new("version")
+                .long("version")
+                .help("Print version")
+                .action(ArgAction::Version),
+        )
         .get_matches();
 
-    let file1 = matches.get_one::<String>("file1").unwrap().to_string();
-    let file2 = matches.get_one::<String>("file2").unwrap().to_string();
+    let files: Vec<String> = matches.get_many::<String>("files").unwrap().cloned().collect();
+    let matrix = matches.get_flag("matrix");
+    // Only two files have columns to suppress or count
+    let column_options = ["suppress_col1", "suppress_col2", "suppress_col3", "changed", "total"];
+    if (files.len() != 2 || matrix) && column_options.iter().any(|id| matches.get_flag(id)) {
+        return Err(From::from("-1, -2, -3, --changed and --total need two files and no -m"));
+    }
 
     let show_col1= !matches.get_flag("suppress_col1");
     let show_col2= !matches.get_flag("suppress_col2");
     let show_col3= !matches.get_flag("suppress_col3");
 
-    let insensitive= matches.get_flag("insensitive");
+    let collation = if matches.get_flag("insensitive") {
+        Collation::Fold
+    } else if matches.get_flag("numeric_sort") {
+        Collation::Numeric
+    } else if matches.get_flag("version_sort") {
+        Collation::Version
+    } else {
+        Collation::Bytes
+    };
     let delimiter = matches.get_one::<String>("delimiter").unwrap().to_string();
+    let fields = match matches.get_one::<String>("field_delimiter") {
+        Some(delimiter) => {
+            let delimiter = match delimiter.as_bytes() {
+                [byte] => *byte,
+                _ => return Err(format!("--field-separator \"{}\" must be a single byte", delimiter).into()),
+            };
+            let key = match matches.get_one::<usize>("key").copied().unwrap_or(1) {
+                0 => return Err(From::from("--key must be a field number from 1")),
+                key => key - 1,
+            };
+            Some(Fields { delimiter, key })
+        }
+        None => None,
+    };
+
+    let check_order = if matches.get_flag("check_order") {
+        CheckOrder::Enabled
+    } else if matches.get_flag("nocheck_order") {
+        CheckOrder::Disabled
+    } else {
+        CheckOrder::Default
+    };
+
+    let buffer_size = matches.get_one::<String>("buffer_size").unwrap();
+    let buffer_size = parse_size(buffer_size)
+        .ok_or_else(|| format!("invalid --buffer-size \"{}\"", buffer_size))?;
 
     Ok(Config{
-        file1,
-        file2,
+        files,
         show_col1,
         show_col2,
         show_col3,
-        insensitive,
+        collation,
         delimiter,
+        check_order,
+        total: matches.get_flag("total"),
+        fields,
+        changed: matches.get_flag("changed"),
+        matrix,
+        zero_terminated: matches.get_flag("zero_terminated"),
+        sort: matches.get_flag("sort"),
+        buffer_size,
     })
 }
+
+// Parses a size as sort -S does: KiB by default, or bytes, KiB, MiB or GiB
+// with a b, K, M or G suffix
+fn parse_size(size: &str) -> Option<usize> {
+    let (number, unit) = match size.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => size.split_at(index),
+        None => (size, "K"),
+    };
+    let unit: usize = match unit {
+        "b" => 1,
+        "K" | "k" => 1 << 10,
+        "M" | "m" => 1 << 20,
+        "G" | "g" => 1 << 30,
+        _ => return None,
+    };
+    number.parse::<usize>().ok()?.checked_mul(unit).filter(|&size| size > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Checks that `lines` are in strictly increasing order
+    fn assert_sorted(collation: Collation, lines: &[&str]) {
+        for pair in lines.windows(2) {
+            assert_eq!(compare(collation, pair[0].as_bytes(), pair[1].as_bytes()), Less, "{:?}", pair);
+            assert_eq!(compare(collation, pair[1].as_bytes(), pair[0].as_bytes()), Greater, "{:?}", pair);
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("10"), Some(10 << 10));
+        assert_eq!(parse_size("100b"), Some(100));
+        assert_eq!(parse_size("2M"), Some(2 << 20));
+        assert_eq!(parse_size("1G"), Some(1 << 30));
+        assert_eq!(parse_size("0"), None);
+        assert_eq!(parse_size("M"), None);
+        assert_eq!(parse_size("1.5M"), None);
+        assert_eq!(parse_size("1T"), None);
+    }
+
+    #[test]
+    fn test_split() {
+        let mut split = Split::new(&Fields { delimiter: b';', key: 0 });
+        let fields = |record: ByteRecord| record.iter().map(|f| String::from_utf8_lossy(f).into_owned()).collect::<Vec<_>>();
+        assert_eq!(fields(split.record(br#"a;"b;c";"d""e""#)), ["a", "b;c", "d\"e"]);
+        // Nothing is left from the line before, however long
+        let long = "x".repeat(5000) + &";".repeat(200);
+        assert_eq!(fields(split.record(long.as_bytes())).len(), 201);
+        assert_eq!(fields(split.record(b"\"open;x")), ["open;x"]);
+        assert_eq!(fields(split.record(b"")), Vec::<String>::new());
+        assert_eq!(fields(split.record(b"a;b\nc")), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_compare_fold() {
+        assert_eq!(compare(Collation::Fold, b"Abc", b"aBC"), Equal);
+        // Folding to upper case puts "_" after letters, as sort -f does
+        assert_sorted(Collation::Fold, &["a", "B", "Z", "_"]);
+    }
+
+    #[test]
+    fn test_compare_numbers() {
+        assert_sorted(Collation::Numeric, &["-10", "-9.5", "-1", "", "-0", "0", "x", "00.01", " 1", "1", "1.0", "1.5", "2", "10"]);
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_sorted(Collation::Version, &[
+            "", ".", "..", ".a", ".a1", ".a.gz", "..a", "1", "1.a", "1.b", "a~", "a", "a.tar", "a.tar.gz",
+            "v1.2", "v1.9", "v1.10", "v2.0~rc1", "v02.0", "v2.0",
+        ]);
+    }
+}
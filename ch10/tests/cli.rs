@@ -1,3 +1,4 @@
+#![allow(deprecated)]
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distr::Alphanumeric, Rng};
@@ -8,6 +9,17 @@ const EMPTY: &str = "tests/inputs/empty.txt";
 const FILE1: &str = "tests/inputs/file1.txt";
 const FILE2: &str = "tests/inputs/file2.txt";
 const BLANK: &str = "tests/inputs/blank.txt";
+const UNSORTED: &str = "tests/inputs/unsorted.txt";
+const UPPER: &str = "tests/inputs/upper.txt";
+const HOSTS_A: &str = "tests/inputs/hosts.a.txt";
+const HOSTS_B: &str = "tests/inputs/hosts.b.txt";
+const HOSTS_C: &str = "tests/inputs/hosts.c.txt";
+const LATIN1: &str = "tests/inputs/latin1.txt";
+const LATIN2: &str = "tests/inputs/latin2.txt";
+const NUL1: &str = "tests/inputs/nul1.txt";
+const NUL2: &str = "tests/inputs/nul2.txt";
+const SHUFFLED1: &str = "tests/inputs/shuffled1.txt";
+const SHUFFLED2: &str = "tests/inputs/shuffled2.txt";
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -105,6 +117,16 @@ fn run_stdin(
     Ok(())
 }
 
+// --------------------------------------------------
+// Compares bytes, for output that is not UTF-8
+fn run_bytes(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read(expected_file)?;
+    let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn empty_empty() -> TestResult {
@@ -341,4 +363,258 @@ fn file1_file2_123_delim() -> TestResult {
 #[test]
 fn blank_file1() -> TestResult {
     run(&[BLANK, FILE1], "tests/expected/blank_file1.out")
-}
\ No newline at end of file
+}
+// --------------------------------------------------
+fn run_unsorted(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read_to_string(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .code(1)
+        .stdout(expected)
+        .stderr(predicate::str::contains(
+            "file 1 is not in sorted order: tests/inputs/unsorted.txt:3",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_file2() -> TestResult {
+    run_unsorted(&[UNSORTED, FILE2], "tests/expected/unsorted_file2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_file2_check_order() -> TestResult {
+    run_unsorted(
+        &["--check-order", UNSORTED, FILE2],
+        "tests/expected/unsorted_file2.check-order.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_file2_nocheck_order() -> TestResult {
+    run(
+        &["--nocheck-order", UNSORTED, FILE2],
+        "tests/expected/unsorted_file2.nocheck-order.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_pairable() -> TestResult {
+    // Only lines not in the other file are checked by default
+    run(&[UNSORTED, UNSORTED], "tests/expected/unsorted_unsorted.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file1_check_order() -> TestResult {
+    run(
+        &["--check-order", FILE1, FILE1],
+        "tests/expected/file1_file1.check-order.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn upper_file2_i() -> TestResult {
+    run(&["-i", UPPER, FILE2], "tests/expected/upper_file2.i.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn numbers1_numbers2_n() -> TestResult {
+    run(
+        &["-n", "tests/inputs/numbers1.txt", "tests/inputs/numbers2.txt"],
+        "tests/expected/numbers1_numbers2.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn versions1_versions2_v() -> TestResult {
+    run(
+        &["-V", "tests/inputs/versions1.txt", "tests/inputs/versions2.txt"],
+        "tests/expected/versions1_versions2.V.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_total() -> TestResult {
+    run(&["--total", FILE1, FILE2], "tests/expected/file1_file2.total.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_3_total_delim() -> TestResult {
+    run(
+        &["--total", "-3", "-d", ":", FILE1, FILE2],
+        "tests/expected/file1_file2.3.total.delim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn stock1_stock2_t() -> TestResult {
+    run(
+        &["-t", ",", "tests/inputs/stock1.csv", "tests/inputs/stock2.csv"],
+        "tests/expected/stock1_stock2.t.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn stock1_stock2_t_changed_total() -> TestResult {
+    run(
+        &["-t", ",", "--changed", "--total", "tests/inputs/stock1.csv", "tests/inputs/stock2.csv"],
+        "tests/expected/stock1_stock2.t.changed.total.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn hosts1_hosts2_t_k2_changed_delim() -> TestResult {
+    run(
+        &[
+            "-t",
+            "\t",
+            "-k",
+            "2",
+            "--changed",
+            "-d",
+            "|",
+            "tests/inputs/hosts1.tsv",
+            "tests/inputs/hosts2.tsv",
+        ],
+        "tests/expected/hosts1_hosts2.t.k2.changed.delim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_key_without_delimiter() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-k", "2", FILE1, FILE2])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--field-separator"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_key() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-t", ",", "-k", "0", FILE1, FILE2])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--key must be a field number from 1"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn hosts_three_files() -> TestResult {
+    run(&[HOSTS_A, HOSTS_B, HOSTS_C], "tests/expected/hosts.abc.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn hosts_three_files_matrix_delim() -> TestResult {
+    run(
+        &["-m", "-d", ",", HOSTS_A, HOSTS_B, HOSTS_C],
+        "tests/expected/hosts.abc.m.delim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_columns_three_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-3", HOSTS_A, HOSTS_B, HOSTS_C])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("need two files"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_latin2() -> TestResult {
+    run_bytes(&[LATIN1, LATIN2], "tests/expected/latin1_latin2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn nul1_nul2_zero_terminated() -> TestResult {
+    run_bytes(&["-z", NUL1, NUL2], "tests/expected/nul1_nul2.z.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn nul1_nul2_zero_terminated_12() -> TestResult {
+    run_bytes(&["--zero-terminated", "-12", NUL1, NUL2], "tests/expected/nul1_nul2.z.12.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn shuffled1_shuffled2_sort() -> TestResult {
+    run(&["--sort", SHUFFLED1, SHUFFLED2], "tests/expected/shuffled1_shuffled2.sort.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn shuffled1_shuffled2_sort_3() -> TestResult {
+    run(&["--sort", "-3", SHUFFLED1, SHUFFLED2], "tests/expected/shuffled1_shuffled2.sort.3.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn shuffled1_shuffled2_sort_temp_files() -> TestResult {
+    // A buffer of a few lines sorts most of each file in temporary files
+    run(
+        &["--sort", "-S", "200b", "-3", SHUFFLED1, SHUFFLED2],
+        "tests/expected/shuffled1_shuffled2.sort.3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn shuffled1_shuffled2_sort_merge_passes() -> TestResult {
+    // A line in each temporary file makes too many to merge at once
+    run(
+        &["--sort", "-S", "1b", SHUFFLED1, SHUFFLED2],
+        "tests/expected/shuffled1_shuffled2.sort.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn shuffled1_stdin_sort() -> TestResult {
+    run_stdin(
+        &["--sort", SHUFFLED1, "-"],
+        SHUFFLED2,
+        "tests/expected/shuffled1_shuffled2.sort.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn nul2_nul1_sort_zero_terminated() -> TestResult {
+    run_bytes(&["--sort", "-S", "1b", "-z", NUL2, NUL1], "tests/expected/nul2_nul1.sort.z.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_buffer_size() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--sort", "-S", "1T", FILE1, FILE2])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid --buffer-size \"1T\""));
+    Ok(())
+}
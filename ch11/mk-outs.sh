@@ -28,12 +28,13 @@ for FILE in $ROOT/*.txt; do
     tail -c +2  $FILE > ${OUT_DIR}/${BASENAME}.c+2.out
 done
 
+# Each file its own word, or tail looks for one file with newlines in its name
 ALL=(
-"$ROOT/twelve.txt
-$ROOT/empty.txt
-$ROOT/one.txt
-$ROOT/three.txt
-$ROOT/two.txt"
+    $ROOT/twelve.txt
+    $ROOT/empty.txt
+    $ROOT/one.txt
+    $ROOT/three.txt
+    $ROOT/two.txt
 )
 
 tail         $ALL > $OUT_DIR/all.out
@@ -52,4 +53,19 @@ tail -n +3    $ALL > $OUT_DIR/all.n+3.out
 tail -c +3    $ALL > $OUT_DIR/all.c+3.out
 tail -c +8    $ALL > $OUT_DIR/all.c+8.out
 tail -c +12   $ALL > $OUT_DIR/all.c+12.out
-tail -n +3 -q $ALL > $OUT_DIR/all.n+3.q.out
\ No newline at end of file
+tail -n +3 -q $ALL > $OUT_DIR/all.n+3.q.out
+
+# -v prints a header even for one file, and the last of -q and -v wins. A
+# file that cannot be opened gets no header, so the next has no blank line
+# before its own.
+tail -v -n 1    $ROOT/one.txt > $OUT_DIR/one.txt.v.n1.out
+tail -q -v -n 1 $ROOT/one.txt $ROOT/two.txt > $OUT_DIR/one_two.q.v.n1.out
+tail -n 1       $ROOT/missing.txt $ROOT/one.txt $ROOT/two.txt > $OUT_DIR/missing_one_two.n1.out
+
+# Output is the bytes read, even bytes that are not UTF-8 or a character
+# cut in two, and -z splits lines on NUL
+tail -n 2     $ROOT/binary.dat > $OUT_DIR/binary.dat.n2.out
+tail -c 5     $ROOT/binary.dat > $OUT_DIR/binary.dat.c5.out
+tail -c +8    $ROOT/binary.dat > $OUT_DIR/binary.dat.c+8.out
+tail -z -n 2  $ROOT/files0.dat > $OUT_DIR/files0.dat.z.n2.out
+tail -z -n +2 $ROOT/files0.dat > $OUT_DIR/files0.dat.z.n+2.out
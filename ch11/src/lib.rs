@@ -1,7 +1,12 @@
 use std::{
     error::Error,
-    fs::File,
-    io::{self, BufRead, BufReader, Read, Seek},
+    collections::VecDeque,
+    fs::{self, File, Metadata},
+    io::{self, BufRead, BufReader, Read, Seek, Write},
+    mem,
+    path::Path,
+    thread,
+    time::Duration,
 };
 use std::io::SeekFrom;
 use clap::{Arg, ArgAction, Command};
@@ -24,143 +29,466 @@ pub struct Config {
     lines: TakeValue,
     bytes: Option<TakeValue>,
     quiet: bool,
+    verbose: bool,
+    zero_terminated: bool,
+    follow: Option<Follow>,
+    // Stop following once this process has exited
+    pid: Option<i32>,
+    sleep_interval: Duration,
+    use_inotify: bool,
 }
 
-fn count_lines_bytes(filename: &str) -> MyResult<(i64, i64)> {
-    let mut file = BufReader::new(File::open(filename)?);
-    let mut num_lines = 0;
-    let mut num_bytes = 0;
+// How -f follows a file: the file opened, even once renamed or deleted, or
+// with -F whatever file has the name, reopened when it is replaced or
+// appears again
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Follow {
+    Descriptor,
+    Name,
+}
+
+// How much of a file is read at a time, looking back from its end for lines
+// or through a stream
+const BLOCK_SIZE: u64 = 64 * 1024;
+
+impl Config {
+    // Whether each file is printed after a header with its name: with
+    // -v, or unless -q for more than one file
+    fn headers(&self) -> bool {
+        self.verbose || (!self.quiet && self.files.len() > 1)
+    }
+
+    // What ends each line
+    fn terminator(&self) -> u8 {
+        if self.zero_terminated { b'\0' } else { b'\n' }
+    }
+}
+
+// Prints the tail of a file, seeking to the last lines or bytes of a
+// regular file, and otherwise reading it through as a stream
+fn print_file(file: &mut File, config: &Config, out: &mut impl Write) -> MyResult<()> {
+    if !file.metadata()?.is_file() {
+        return print_stream(BufReader::new(file), config, out);
+    }
+    let len = file.seek(SeekFrom::End(0))?;
+    let start = match (&config.bytes, &config.lines) {
+        (Some(TakeValue::TakeNum(num)), _) if *num < 0 => len.saturating_sub(num.unsigned_abs()),
+        (Some(TakeValue::TakeNum(num)), _) if *num > 0 => len.min(*num as u64 - 1),
+        (None, TakeValue::TakeNum(num)) if *num < 0 => {
+            last_lines_start(&mut *file, num.unsigned_abs(), len, config.terminator())?
+        }
+        _ => {
+            file.seek(SeekFrom::Start(0))?;
+            return print_stream(BufReader::new(file), config, out);
+        }
+    };
+    file.seek(SeekFrom::Start(start))?;
+    io::copy(file, out)?;
+    Ok(())
+}
+
+// Prints the tail of an input that cannot seek, such as a pipe, in one
+// pass: the last lines or bytes are kept in a ring buffer of that many,
+// and with +K what comes before is skipped
+fn print_stream(mut file: impl BufRead, config: &Config, out: &mut impl Write) -> MyResult<()> {
+    let terminator = config.terminator();
+    match &config.bytes {
+        Some(TakeValue::PlusZero) => {
+            io::copy(&mut file, out)?;
+        }
+        Some(TakeValue::TakeNum(0)) => (),
+        Some(TakeValue::TakeNum(num)) if *num > 0 => {
+            io::copy(&mut file.by_ref().take(*num as u64 - 1), &mut io::sink())?;
+            io::copy(&mut file, out)?;
+        }
+        Some(TakeValue::TakeNum(num)) => print_last_bytes(file, num.unsigned_abs() as usize, out)?,
+        None => match config.lines {
+            TakeValue::PlusZero => {
+                io::copy(&mut file, out)?;
+            }
+            TakeValue::TakeNum(0) => (),
+            TakeValue::TakeNum(num) if num > 0 => {
+                for _ in 1..num {
+                    if file.skip_until(terminator)? == 0 {
+                        break;
+                    }
+                }
+                io::copy(&mut file, out)?;
+            }
+            TakeValue::TakeNum(num) => print_last_lines(file, num.unsigned_abs() as usize, terminator, out)?,
+        },
+    }
+    Ok(())
+}
+
+// Where the last `num` lines of a file of `len` bytes start, reading
+// blocks back from the end until enough terminators are found
+fn last_lines_start(mut file: impl Read + Seek, num: u64, len: u64, terminator: u8) -> io::Result<u64> {
+    let mut buf = vec![0; BLOCK_SIZE as usize];
+    let mut found = 0;
+    let mut end = len;
+    while end > 0 {
+        let start = end.saturating_sub(BLOCK_SIZE);
+        let block = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(block)?;
+        for (i, byte) in block.iter().enumerate().rev() {
+            let offset = start + i as u64;
+            // A terminator at the end only ends the last line
+            if *byte == terminator && offset + 1 < len {
+                found += 1;
+                if found == num {
+                    return Ok(offset + 1);
+                }
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+// Prints the last `num` lines of `file`, holding no more than that many
+fn print_last_lines(mut file: impl BufRead, num: usize, terminator: u8, out: &mut impl Write) -> io::Result<()> {
+    let mut last = VecDeque::new();
     let mut buf = Vec::new();
 
     loop {
-        let bytes_read = file.read_until(b'\n', &mut buf)?;
+        let bytes_read = file.read_until(terminator, &mut buf)?;
         if bytes_read == 0 {
             break;
         }
 
-        num_lines += 1;
-        num_bytes += bytes_read as i64;
-        buf.clear();
+        if last.len() == num {
+            last.pop_front();
+        }
+        last.push_back(mem::take(&mut buf));
+    }
+    for line in last {
+        out.write_all(&line)?;
     }
-    Ok((num_lines, num_bytes))
+    Ok(())
 }
 
-fn print_lines(
-    mut file: impl BufRead,
-    num_lines: &TakeValue,
-    total_lines: i64,
-) -> MyResult<()> {
-    if let Some(start) = get_start_index(num_lines, total_lines) {
-        let mut line_num = 0;
-        let mut buf = Vec::new();
+// Prints the last `num` bytes of `file`, holding no more than that many
+fn print_last_bytes(mut file: impl Read, num: usize, out: &mut impl Write) -> io::Result<()> {
+    let mut last = VecDeque::new();
+    let mut buf = vec![0; BLOCK_SIZE as usize];
 
-        loop {
-            let bytes_read = file.read_until(b'\n', &mut buf)?;
-            if bytes_read == 0 {
-                break;
-            }
+    loop {
+        let bytes_read = file.read(&mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+
+        last.extend(&buf[..bytes_read]);
+        last.drain(..last.len().saturating_sub(num));
+    }
+    let (front, back) = last.as_slices();
+    out.write_all(front)?;
+    out.write_all(back)
+}
+
+pub fn run(config: Config) -> MyResult<()> {
+    // Like GNU tail, -n 0 or -c 0 without -f opens no files, so prints
+    // neither headers nor errors
+    let num = config.bytes.as_ref().unwrap_or(&config.lines);
+    if *num == TakeValue::TakeNum(0) && config.follow.is_none() {
+        return Ok(());
+    }
 
-            if line_num >= start {
-                print!("{}", String::from_utf8_lossy(&buf));
+    let mut followed = vec![];
+    let mut out = io::stdout().lock();
+    // As with GNU tail, only files that open get a header, and a blank
+    // line comes before every header but the first
+    let mut first_header = true;
+    let mut print_header = |filename: &str, out: &mut io::StdoutLock| -> io::Result<()> {
+        if config.headers() {
+            writeln!(
+                out,
+                "{}==> {} <==",
+                if first_header { "" } else { "\n" },
+                if filename == "-" { "standard input" } else { filename }
+            )?;
+            first_header = false;
+        }
+        Ok(())
+    };
+    for filename in &config.files {
+        // Like GNU tail, -f stops at the end of a pipe
+        if filename == "-" {
+            print_header(filename, &mut out)?;
+            print_stream(io::stdin().lock(), &config, &mut out)?;
+            continue;
+        }
+        match File::open(filename) {
+            Err(err) => {
+                eprintln!("{}: {}", filename, err);
+                if config.follow == Some(Follow::Name) {
+                    followed.push(Followed { filename, file: None, position: 0 });
+                }
             }
+            Ok(mut file) => {
+                print_header(filename, &mut out)?;
+                let len = file.metadata()?.len();
+                print_file(&mut file, &config, &mut out)?;
+                if config.follow.is_some() {
+                    // Following starts where the file ended, unless more
+                    // was printed since
+                    let position = file.stream_position()?.max(len);
+                    file.seek(SeekFrom::Start(position))?;
+                    followed.push(Followed { filename, file: Some(file), position });
+                }
+            }
+        }
+    }
 
-            line_num += 1;
-            buf.clear();
+    if let Some(follow) = config.follow {
+        if followed.is_empty() && config.files.iter().any(|filename| filename != "-") {
+            return Err(From::from("no files remaining"));
+        }
+        if !followed.is_empty() {
+            follow_files(&config, follow, followed, &mut out)?;
         }
     }
+    out.flush()?;
     Ok(())
 }
 
-fn get_start_index(
-    take_val: &TakeValue,
-    total: i64,
-) -> Option<u64> {
-    match take_val {
-        TakeValue::PlusZero => {
-            if total > 0 {
-                Some(0)
-            } else {
-                None
-            }
+// A file being followed, and how much of it has been printed
+struct Followed<'a> {
+    filename: &'a str,
+    file: Option<File>,
+    position: u64,
+}
+
+impl Followed<'_> {
+    // Prints what was written to the file since it was last read, all of it
+    // if the file was truncated, after a header if another file was last
+    fn print_new(&mut self, index: usize, last: &mut Option<usize>, headers: bool, out: &mut impl Write) -> io::Result<()> {
+        let Some(file) = &mut self.file else {
+            return Ok(());
+        };
+        let len = file.metadata()?.len();
+        if len < self.position {
+            eprintln!("{}: file truncated", self.filename);
+            self.position = file.seek(SeekFrom::Start(0))?;
         }
-        TakeValue::TakeNum(num) => {
-            if num == &0 || total == 0 || num > &total {
-                None
-            } else {
-                let start = if num < &0 { total + num } else { num - 1};
-                Some(if start < 0 { 0 } else { start as u64})
+        if len == self.position {
+            return Ok(());
+        }
+        if headers && *last != Some(index) {
+            writeln!(out, "{}==> {} <==", if last.is_some() { "\n" } else { "" }, self.filename)?;
+        }
+        *last = Some(index);
+        self.position += io::copy(file, out)?;
+        out.flush()
+    }
+
+    // Opens the file that now has the name, if it is not the one open,
+    // telling if it did
+    fn reopen(&mut self) -> bool {
+        let current = self.file.as_ref().and_then(|file| file.metadata().ok());
+        match fs::metadata(self.filename) {
+            Err(err) => {
+                if self.file.take().is_some() {
+                    eprintln!("{}: has become inaccessible: {}", self.filename, err);
+                }
+                false
             }
+            Ok(metadata) if current.as_ref().is_some_and(|current| same_file(current, &metadata)) => false,
+            Ok(_) => match File::open(self.filename) {
+                Err(_) => false,
+                Ok(file) => {
+                    if current.is_some() {
+                        eprintln!("{}: has been replaced; following new file", self.filename);
+                    } else {
+                        eprintln!("{}: has appeared; following new file", self.filename);
+                    }
+                    self.file = Some(file);
+                    self.position = 0;
+                    true
+                }
+            },
         }
     }
 }
 
-fn print_bytes<T: Read + Seek>(
-    mut file: T,
-    num_bytes: &TakeValue,
-    total_bytes: i64,
-) -> MyResult<()> {
-    if let Some(start) = get_start_index(num_bytes, total_bytes) {
-        file.seek(SeekFrom::Start(start))?;
-        let mut buf = Vec::new();
-        file.read_to_end(&mut buf)?;
-        if !buf.is_empty() {
-            print!("{}", String::from_utf8_lossy(&buf));
+// Prints what is written to the files until the --pid process exits, if
+// any, waiting for changes with inotify or by sleeping between checks
+fn follow_files(config: &Config, follow: Follow, mut files: Vec<Followed>, out: &mut io::StdoutLock) -> MyResult<()> {
+    let watcher = if config.use_inotify { inotify::Watcher::new().ok() } else { None };
+    let watch = |followed: &Followed| {
+        if let Some(watcher) = &watcher {
+            let path = Path::new(followed.filename);
+            // Errors only leave the file to be checked every -s seconds
+            let _ = watcher.watch(path, false);
+            if follow == Follow::Name {
+                let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+                let _ = watcher.watch(dir.unwrap_or(Path::new(".")), true);
+            }
+        }
+    };
+    files.iter().for_each(watch);
+
+    let headers = config.headers();
+    let mut last = files.iter().rposition(|followed| followed.file.is_some());
+    out.flush()?;
+    loop {
+        // What the process wrote before it exited is printed too
+        let running = config.pid.is_none_or(process_exists);
+        for (index, followed) in files.iter_mut().enumerate() {
+            let print_new = |followed: &mut Followed, last: &mut Option<usize>, out: &mut io::StdoutLock| {
+                followed.print_new(index, last, headers, out)
+                    .map_err(|e| format!("{}: {}", followed.filename, e))
+            };
+            print_new(followed, &mut last, out)?;
+            if follow == Follow::Name && followed.reopen() {
+                watch(followed);
+                print_new(followed, &mut last, out)?;
+            }
+        }
+        if !running {
+            return Ok(());
+        }
+        match &watcher {
+            Some(watcher) => watcher.wait(config.sleep_interval)?,
+            None => thread::sleep(config.sleep_interval),
         }
     }
-    Ok(())
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let num_files = config.files.len();
-    for (file_num, filename) in config.files.iter().enumerate() {
-        match File::open(filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(file) => {
-                if !config.quiet && num_files > 1 {
-                   println!(
-                       "{}==> {} <==",
-                       if file_num > 0 { "\n" } else { "" },
-                       filename
-                   );
-                }
+#[cfg(unix)]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    (a.dev(), a.ino()) == (b.dev(), b.ino())
+}
 
-                let (total_lines, total_bytes) = count_lines_bytes(&filename)?;
-                let file = BufReader::new(file);
-                if let Some(num_bytes) = &config.bytes {
-                    print_bytes(file, num_bytes, total_bytes)?;
-                } else {
-                    print_lines(file, &config.lines, total_lines)?;
-                }
+// Only truncation is noticed where files cannot be told apart
+#[cfg(not(unix))]
+fn same_file(_: &Metadata, _: &Metadata) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn process_exists(pid: i32) -> bool {
+    // Signal 0 only checks that the process could be signalled
+    unsafe { libc::kill(pid, 0) == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM) }
+}
+
+#[cfg(not(unix))]
+fn process_exists(_: i32) -> bool {
+    true
+}
+
+// Waits for files to change with inotify, which only Linux has
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::{
+        ffi::CString,
+        io,
+        os::{fd::{AsRawFd, FromRawFd, OwnedFd}, unix::ffi::OsStrExt},
+        path::Path,
+        time::Duration,
+    };
+
+    pub struct Watcher {
+        fd: OwnedFd,
+    }
+
+    impl Watcher {
+        pub fn new() -> io::Result<Watcher> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
             }
+            Ok(Watcher { fd: unsafe { OwnedFd::from_raw_fd(fd) } })
+        }
+
+        // Watches a file for writes and for being deleted or renamed, or a
+        // directory for files created, deleted or renamed in it
+        pub fn watch(&self, path: &Path, dir: bool) -> io::Result<()> {
+            let mask = if dir {
+                libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO
+            } else {
+                libc::IN_MODIFY | libc::IN_ATTRIB | libc::IN_DELETE_SELF | libc::IN_MOVE_SELF
+            };
+            let path = CString::new(path.as_os_str().as_bytes())?;
+            if unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), path.as_ptr(), mask) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        // Waits up to `timeout` for any change, and forgets what changed:
+        // every file is checked after
+        pub fn wait(&self, timeout: Duration) -> io::Result<()> {
+            let mut pollfd = libc::pollfd { fd: self.fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+            let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+            if unsafe { libc::poll(&mut pollfd, 1, timeout) } < 0 {
+                let err = io::Error::last_os_error();
+                return if err.kind() == io::ErrorKind::Interrupted { Ok(()) } else { Err(err) };
+            }
+            let mut events = [0u8; 4096];
+            while unsafe { libc::read(self.fd.as_raw_fd(), events.as_mut_ptr().cast(), events.len()) } > 0 {}
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod inotify {
+    use std::{io, path::Path, time::Duration};
+
+    pub struct Watcher;
+
+    impl Watcher {
+        pub fn new() -> io::Result<Watcher> {
+            Err(io::ErrorKind::Unsupported.into())
+        }
+
+        pub fn watch(&self, _: &Path, _: bool) -> io::Result<()> {
+            Ok(())
+        }
+
+        pub fn wait(&self, _: Duration) -> io::Result<()> {
+            Ok(())
         }
     }
-    Ok(())
 }
 
 fn parse_num(val: &str) -> MyResult<TakeValue> {
     // below will init a Regex for every call of parse_num
     // let num_re = Regex::new(r"^([+-])?(\d+)$").unwrap();
     // use once_cell
-    let num_re = NUM_RE.get_or_init(|| Regex::new(r"^([+-])?(\d+)$").unwrap());
+    let num_re = NUM_RE.get_or_init(|| Regex::new(r"^([+-])?(\d+)(b|[kKmMGTPE](?:B|iB)?)?$").unwrap());
 
     match num_re.captures(val) {
         Some(caps) => {
             let sign = caps.get(1).map_or("-", |m| m.as_str());
-            let num = format!("{}{}", sign, caps.get(2).unwrap().as_str());
-            if let Ok(val) = num.parse() {
-                if sign == "-" && val == 0 {
-                    Ok(TakeValue::PlusZero)
-                } else {
-                    Ok(TakeValue::TakeNum(val))
-                }
-            } else {
-                Err(From::from(val))
+            let multiplier = caps.get(3).map_or(1, |m| suffix_multiplier(m.as_str()));
+            match caps[2].parse::<i64>().ok().and_then(|num| num.checked_mul(multiplier)) {
+                Some(0) if sign == "+" => Ok(TakeValue::PlusZero),
+                Some(num) if sign == "+" => Ok(TakeValue::TakeNum(num)),
+                Some(num) => Ok(TakeValue::TakeNum(-num)),
+                None => Err(From::from(val)),
             }
         }
         _ => Err(From::from(val)),
     }
 }
 
+// What a number suffix multiplies it by, as for GNU tail: b is 512 bytes,
+// a letter and B a power of 1000, and a letter alone or with iB a power of
+// 1024
+fn suffix_multiplier(suffix: &str) -> i64 {
+    if suffix == "b" {
+        return 512;
+    }
+    let power = "KMGTPE".find(suffix[..1].to_ascii_uppercase().as_str()).unwrap() as u32 + 1;
+    let base: i64 = if suffix.ends_with("iB") || !suffix.ends_with('B') { 1024 } else { 1000 };
+    base.pow(power)
+}
+
 pub fn get_args() -> MyResult<Config> {
     let matches = Command::new("ch11-tailr")
         .version("0.1.0")
@@ -169,16 +497,16 @@ pub fn get_args() -> MyResult<Config> {
         .arg(
             Arg::new("files")
                 .value_name("FILES")
-                .help("Input file(s) ")
+                .help("Input file(s), or - for standard input")
                 .num_args(1..)
-                .required(true)
+                .default_value("-")
         )
         .arg(
             Arg::new("lines")
                 .short('n')
                 .long("lines")
                 .value_name("LINES")
-                .help("Number of lines")
+                .help("Number of lines, with an optional suffix: b (512), kB, K or KiB, MB, M or MiB, and so on to E")
                 .default_value("10")
         )
         .arg(
@@ -187,19 +515,74 @@ pub fn get_args() -> MyResult<Config> {
                 .long("bytes")
                 .value_name("BYTES")
                 .conflicts_with("lines")
-                .help("Number of bytes")
+                .help("Number of bytes, with the same suffixes as --lines")
         )
         .arg(
             Arg::new("quiet")
                 .short('q')
                 .long("quiet")
+                .visible_alias("silent")
                 .help("Suppress headers")
+                .action(ArgAction::SetTrue)
+                .overrides_with("verbose")
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Always print headers, even for one file")
+                .action(ArgAction::SetTrue)
+                .overrides_with("quiet")
+        )
+        .arg(
+            Arg::new("zero_terminated")
+                .short('z')
+                .long("zero-terminated")
+                .help("Line delimiter is NUL, not newline")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("follow")
+                .short('f')
+                .long("follow")
+                .value_name("HOW")
+                .help("Print data as it is appended, following the file descriptor or the file name")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("descriptor")
+                .value_parser(["descriptor", "name"])
+        )
+        .arg(
+            Arg::new("follow_name")
+                .short('F')
+                .help("Same as --follow=name: reopen files that are rotated, truncated or reappear")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("pid")
+                .long("pid")
+                .value_name("PID")
+                .help("With -f, stop after process PID exits")
+                .value_parser(clap::value_parser!(i32).range(1..))
+        )
+        .arg(
+            Arg::new("sleep_interval")
+                .short('s')
+                .long("sleep-interval")
+                .value_name("SECONDS")
+                .help("With -f, check files every SECONDS [default: 1.0]")
+        )
+        .arg(
+            Arg::new("disable_inotify")
+                .long("disable-inotify")
+                .help("With -f, poll files every -s SECONDS instead of waiting for changes with inotify")
+                .action(ArgAction::SetTrue)
         )
         .get_matches();
 
     let lines = matches
         .get_one::<String>("lines")
-        .map(|s| parse_num(&s))
+        .map(|s| parse_num(s))
         .transpose()
         .map_err(|e| format!("illegal line count -- {}", e))?
         .unwrap();
@@ -211,16 +594,41 @@ pub fn get_args() -> MyResult<Config> {
 
     let bytes= matches
         .get_one::<String>("bytes")
-        .map(|s| parse_num(&s))
+        .map(|s| parse_num(s))
         .transpose()
         .map_err(|e| format!("illegal byte count -- {}", e))?;
 
     let quiet = matches.get_flag("quiet");
+    let verbose = matches.get_flag("verbose");
+
+    let follow = if matches.get_flag("follow_name") {
+        Some(Follow::Name)
+    } else {
+        matches.get_one::<String>("follow").map(|how| match how.as_str() {
+            "name" => Follow::Name,
+            _ => Follow::Descriptor,
+        })
+    };
+
+    let sleep_interval = match matches.get_one::<String>("sleep_interval") {
+        Some(seconds) => seconds
+            .parse()
+            .ok()
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+            .ok_or_else(|| format!("invalid number of seconds -- {}", seconds))?,
+        None => Duration::from_secs(1),
+    };
 
     Ok(Config {
         files,
         lines,
         bytes,
         quiet,
+        verbose,
+        zero_terminated: matches.get_flag("zero_terminated"),
+        follow,
+        pid: matches.get_one::<i32>("pid").copied(),
+        sleep_interval,
+        use_inotify: !matches.get_flag("disable_inotify"),
     })
 }
\ No newline at end of file
@@ -3,7 +3,11 @@ use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distr::Alphanumeric, Rng};
 use std::fs::{self, File};
-use std::io::Read;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{self, Output, Stdio};
+use std::thread;
+use std::time::Duration;
 
 const PRG: &str = "ch11-tailr";
 const EMPTY: &str = "tests/inputs/empty.txt";
@@ -11,6 +15,8 @@ const ONE: &str = "tests/inputs/one.txt";
 const TWO: &str = "tests/inputs/two.txt";
 const THREE: &str = "tests/inputs/three.txt";
 const TWELVE: &str = "tests/inputs/twelve.txt";
+const BINARY: &str = "tests/inputs/binary.dat";
+const FILES0: &str = "tests/inputs/files0.dat";
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -35,13 +41,8 @@ fn gen_bad_file() -> String {
 
 // --------------------------------------------------
 #[test]
-fn dies_no_args() -> TestResult {
-    Command::cargo_bin(PRG)?
-        .assert()
-        .failure()
-        .stderr(predicate::str::contains("Usage"));
-
-    Ok(())
+fn no_args_reads_stdin() -> TestResult {
+    run_stdin(&[], TWELVE, "tests/expected/twelve.txt.out")
 }
 
 // --------------------------------------------------
@@ -115,6 +116,32 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+// Compares bytes, for output that is not UTF-8
+fn run_bytes(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read(expected_file)?;
+    let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_stdin(args: &[&str], input_file: &str, expected_file: &str) -> TestResult {
+    let input = fs::read(input_file)?;
+    let expected = fs::read(expected_file)?;
+    let output = Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input)
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&expected));
+
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn empty() -> TestResult {
@@ -828,4 +855,436 @@ fn multiple_files_c_plus_3() -> TestResult {
         &["-c", "+3", TWELVE, EMPTY, ONE, THREE, TWO],
         "tests/expected/all.c+3.out",
     )
-}
\ No newline at end of file
+}
+
+// --------------------------------------------------
+#[test]
+fn last_lines_across_blocks() -> TestResult {
+    // Many times the size of a block read back from the end
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("big.txt");
+    let lines: Vec<String> = (0..100_000).map(|i| format!("line {i}\n")).collect();
+    fs::write(&path, lines.concat())?;
+
+    for num in [1, 10, 20_000, 99_999, 100_000, 200_000] {
+        let expected = lines[lines.len().saturating_sub(num)..].concat();
+        Command::cargo_bin(PRG)?
+            .args(["-n", &num.to_string()])
+            .arg(&path)
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn last_lines_fifo() -> TestResult {
+    // A FIFO cannot seek, so its last lines are kept as it is read
+    let dir = tempfile::tempdir()?;
+    let fifo = dir.path().join("fifo");
+    assert!(process::Command::new("mkfifo").arg(&fifo).status()?.success());
+    let writer = {
+        let fifo = fifo.clone();
+        thread::spawn(move || fs::write(fifo, fs::read(TWELVE)?))
+    };
+    let expected = fs::read_to_string("tests/expected/twelve.txt.n3.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-n", "3"])
+        .arg(&fifo)
+        .assert()
+        .success()
+        .stdout(expected);
+    writer.join().unwrap()?;
+    Ok(())
+}
+
+// --------------------------------------------------
+// Runs tailr in `dir` until a process it watches with --pid exits, calling
+// `change` once tailr has printed the first `lines` lines
+fn run_follow(
+    args: &[&str],
+    dir: &Path,
+    lines: usize,
+    change: impl FnOnce(&Path) -> io::Result<()>,
+) -> Result<Output, Box<dyn std::error::Error>> {
+    let mut watched = process::Command::new("cat")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    let mut tailr = process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(args)
+        .args(["-s", "0.1", "--pid", &watched.id().to_string()])
+        .current_dir(dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut stdout = BufReader::new(tailr.stdout.take().unwrap());
+    let mut printed = String::new();
+    for _ in 0..lines {
+        stdout.read_line(&mut printed)?;
+    }
+    change(dir)?;
+    thread::sleep(Duration::from_millis(500));
+
+    // cat exits at the end of its input
+    drop(watched.stdin.take());
+    watched.wait()?;
+    stdout.read_to_string(&mut printed)?;
+    let mut output = tailr.wait_with_output()?;
+    output.stdout = printed.into_bytes();
+    Ok(output)
+}
+
+// --------------------------------------------------
+fn append(path: &Path, text: &str) -> io::Result<()> {
+    fs::OpenOptions::new().append(true).open(path)?.write_all(text.as_bytes())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_appended() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("log"), "one\ntwo\n")?;
+    let output = run_follow(&["-f", "-n", "1", "log"], dir.path(), 1, |dir| {
+        append(&dir.join("log"), "three\n")?;
+        thread::sleep(Duration::from_millis(200));
+        append(&dir.join("log"), "four")
+    })?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "two\nthree\nfour");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_truncated() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("log"), "old line\n")?;
+    let output = run_follow(&["--follow", "log"], dir.path(), 1, |dir| {
+        File::create(dir.join("log"))?;
+        thread::sleep(Duration::from_millis(300));
+        append(&dir.join("log"), "new\n")
+    })?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "old line\nnew\n");
+    assert_eq!(String::from_utf8(output.stderr)?, "log: file truncated\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_descriptor_renamed() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("log"), "old\n")?;
+    let output = run_follow(&["-f", "log"], dir.path(), 1, |dir| {
+        fs::rename(dir.join("log"), dir.join("log.1"))?;
+        fs::write(dir.join("log"), "new\n")?;
+        append(&dir.join("log.1"), "older\n")
+    })?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "old\nolder\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+fn follow_name_rotated(args: &[&str]) -> TestResult {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("log"), "old\n")?;
+    let output = run_follow(args, dir.path(), 1, |dir| {
+        append(&dir.join("log"), "older\n")?;
+        fs::rename(dir.join("log"), dir.join("log.1"))?;
+        fs::write(dir.join("log"), "new\n")
+    })?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "old\nolder\nnew\n");
+    assert!(String::from_utf8(output.stderr)?.contains("log: has"));
+    Ok(())
+}
+
+#[test]
+fn follow_name_rotated_inotify() -> TestResult {
+    follow_name_rotated(&["-F", "log"])
+}
+
+#[test]
+fn follow_name_rotated_polling() -> TestResult {
+    follow_name_rotated(&["--follow=name", "--disable-inotify", "log"])
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_name_appeared() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("a"), "a1\n")?;
+    let output = run_follow(&["-F", "-q", "b", "a"], dir.path(), 1, |dir| fs::write(dir.join("b"), "b1\n"))?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "a1\nb1\n");
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.starts_with("b: No such file or directory"));
+    assert!(stderr.ends_with("b: has appeared; following new file\n"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_multiple_files() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("a"), "a1\n")?;
+    fs::write(dir.path().join("b"), "b1\n")?;
+    let output = run_follow(&["-f", "a", "b"], dir.path(), 5, |dir| {
+        append(&dir.join("b"), "b2\n")?;
+        thread::sleep(Duration::from_millis(200));
+        append(&dir.join("a"), "a2\n")
+    })?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "==> a <==\na1\n\n==> b <==\nb1\nb2\n\n==> a <==\na2\n"
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_pid_exited() -> TestResult {
+    let mut exited = process::Command::new("true").spawn()?;
+    exited.wait()?;
+    let pid = exited.id().to_string();
+    run(&["-f", "--pid", &pid, ONE], "tests/expected/one.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_follow_no_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-f", &gen_bad_file()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no files remaining"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_sleep_interval() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "-s", "soon", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid number of seconds -- soon"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_n3() -> TestResult {
+    run_stdin(&["-n", "3", "-"], TWELVE, "tests/expected/twelve.txt.n3.out")
+}
+
+#[test]
+fn stdin_n200() -> TestResult {
+    run_stdin(&["-n", "200"], TWELVE, "tests/expected/twelve.txt.n200.out")
+}
+
+#[test]
+fn stdin_n0() -> TestResult {
+    run_stdin(&["-n", "0"], TWELVE, "tests/expected/twelve.txt.n0.out")
+}
+
+#[test]
+fn stdin_n_plus_2() -> TestResult {
+    run_stdin(&["-n", "+2"], TWELVE, "tests/expected/twelve.txt.n+2.out")
+}
+
+#[test]
+fn stdin_c8() -> TestResult {
+    run_stdin(&["-c", "8"], TWELVE, "tests/expected/twelve.txt.c8.out")
+}
+
+#[test]
+fn stdin_c200() -> TestResult {
+    run_stdin(&["-c", "200"], TWELVE, "tests/expected/twelve.txt.c200.out")
+}
+
+#[test]
+fn stdin_c_plus_2() -> TestResult {
+    run_stdin(&["-c", "+2", "-"], TWELVE, "tests/expected/twelve.txt.c+2.out")
+}
+
+#[test]
+fn stdin_c_plus_200() -> TestResult {
+    run_stdin(&["-c", "+200"], TWELVE, "tests/expected/empty.txt.out")
+}
+
+#[test]
+fn stdin_follow() -> TestResult {
+    // There is nothing more to follow at the end of a pipe
+    run_stdin(&["-f", "-n", "3"], TWELVE, "tests/expected/twelve.txt.n3.out")
+}
+
+#[test]
+fn stdin_with_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "1", ONE, "-"])
+        .write_stdin("first\nsecond\n")
+        .assert()
+        .success()
+        .stdout("==> tests/inputs/one.txt <==\nÖne line, four wordś.\n\n==> standard input <==\nsecond\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_n2() -> TestResult {
+    run_bytes(&["-n", "2", BINARY], "tests/expected/binary.dat.n2.out")
+}
+
+#[test]
+fn binary_c5() -> TestResult {
+    // Starts in the middle of a character
+    run_bytes(&["-c", "5", BINARY], "tests/expected/binary.dat.c5.out")
+}
+
+#[test]
+fn binary_c_plus_8() -> TestResult {
+    run_bytes(&["-c", "+8", BINARY], "tests/expected/binary.dat.c+8.out")
+}
+
+#[test]
+fn binary_stdin_c5() -> TestResult {
+    let expected = fs::read("tests/expected/binary.dat.c5.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-c", "5"])
+        .write_stdin(fs::read(BINARY)?)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn files0_z_n2() -> TestResult {
+    run_bytes(&["-z", "-n", "2", FILES0], "tests/expected/files0.dat.z.n2.out")
+}
+
+#[test]
+fn files0_zero_terminated_n_plus_2() -> TestResult {
+    run_bytes(&["--zero-terminated", "-n", "+2", FILES0], "tests/expected/files0.dat.z.n+2.out")
+}
+
+#[test]
+fn files0_stdin_z_n2() -> TestResult {
+    let expected = fs::read("tests/expected/files0.dat.z.n2.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-n", "2"])
+        .write_stdin(fs::read(FILES0)?)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn one_verbose_n1() -> TestResult {
+    run(&["-v", "-n", "1", ONE], "tests/expected/one.txt.v.n1.out")
+}
+
+#[test]
+fn one_two_quiet_then_verbose() -> TestResult {
+    run(&["-q", "--verbose", "-n", "1", ONE, TWO], "tests/expected/one_two.q.v.n1.out")
+}
+
+#[test]
+fn one_two_verbose_then_silent() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-v", "--silent", "-n", "1", ONE, TWO])
+        .assert()
+        .success()
+        .stdout("Öne line, four wordś.\nFour words.\n");
+    Ok(())
+}
+
+#[test]
+fn missing_first_no_blank_line() -> TestResult {
+    run(
+        &["-n", "1", "tests/inputs/missing.txt", ONE, TWO],
+        "tests/expected/missing_one_two.n1.out",
+    )
+}
+
+#[test]
+fn stdin_verbose() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .arg("-v")
+        .write_stdin("hi\n")
+        .assert()
+        .success()
+        .stdout("==> standard input <==\nhi\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn size_suffixes() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("big.dat");
+    let data: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(&path, &data)?;
+
+    for (num, size) in [
+        ("1b", 512),
+        ("2k", 2048),
+        ("2K", 2048),
+        ("2kB", 2000),
+        ("2KiB", 2048),
+        ("1M", 1 << 20),
+        ("1MB", 1_000_000),
+        ("1MiB", 1 << 20),
+        ("1G", data.len()),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(["-c", num])
+            .arg(&path)
+            .assert()
+            .success()
+            .stdout(data[data.len().saturating_sub(size)..].to_vec());
+    }
+    Command::cargo_bin(PRG)?
+        .args(["-c", "+1kB"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(data[999..].to_vec());
+    Ok(())
+}
+
+#[test]
+fn lines_suffix() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("lines.txt");
+    let lines: Vec<String> = (0..5000).map(|i| format!("{i}\n")).collect();
+    fs::write(&path, lines.concat())?;
+    Command::cargo_bin(PRG)?
+        .args(["-n", "2K"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(lines[5000 - 2048..].concat());
+    Ok(())
+}
+
+#[test]
+fn dies_bad_suffix() -> TestResult {
+    for (option, bad, kind) in [("-n", "1X", "line"), ("-c", "2KB2", "byte"), ("-c", "1Ki", "byte")] {
+        Command::cargo_bin(PRG)?
+            .args([option, bad, ONE])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!("illegal {kind} count -- {bad}")));
+    }
+    Ok(())
+}
@@ -5,6 +5,13 @@ set -U
 CSV="tests/inputs/movies1.csv"
 TSV="tests/inputs/movies1.tsv"
 BOOKS="tests/inputs/books.tsv"
+MIXED="tests/inputs/mixed.tsv"
+QUOTES="tests/inputs/quotes.tsv"
+MOVIES2="tests/inputs/movies2.csv"
+BOOKS_TXT="tests/inputs/books.txt"
+LOG="tests/inputs/app.log"
+COLORS="tests/inputs/colors.txt"
+UNICODE="tests/inputs/unicode.txt"
 OUT_DIR="tests/expected"
 
 [[ ! -d "$OUT_DIR" ]] && mkdir -p "$OUT_DIR"
@@ -15,6 +22,33 @@ for FLD in 1 2 3 1-2 2-3 1-3; do
     cut -f $FLD -d , $CSV > "$OUT_DIR/$(basename $CSV).f${FLD}.dcomma.out"
 done
 
+for FLD in 2-2 -2 2-; do
+    cut -f $FLD      $TSV > "$OUT_DIR/$(basename $TSV).f${FLD}.out"
+    cut -f $FLD -d , $CSV > "$OUT_DIR/$(basename $CSV).f${FLD}.dcomma.out"
+done
+
+for FLD in 1 2 1,3; do
+    cut -f $FLD --complement $TSV > "$OUT_DIR/$(basename $TSV).f${FLD}.complement.out"
+done
+
+cut -f 1,3 --output-delimiter ' | ' $TSV > "$OUT_DIR/$(basename $TSV).f1,3.dpipe.out"
+cut -f 1,3 -d , --output-delimiter ';' $CSV > "$OUT_DIR/$(basename $CSV).f1,3.dsemi.out"
+cut -f 2    $MIXED > "$OUT_DIR/$(basename $MIXED).f2.out"
+cut -f 2 -s $MIXED > "$OUT_DIR/$(basename $MIXED).f2.s.out"
+
+# --mode plain splits like cut, ignoring quotes
+for FLD in 2 3 2-; do
+    cut -f $FLD $QUOTES > "$OUT_DIR/$(basename $QUOTES).f${FLD}.plain.out"
+done
+cut -f 1,3 -d , $MOVIES2 > "$OUT_DIR/$(basename $MOVIES2).f1,3.dcomma.plain.out"
+
+# cut only splits on single bytes, awk stands in for the other delimiters
+awk -F ' [|] ' -v OFS=' | ' '{print $2, $4}' $LOG > "$OUT_DIR/$(basename $LOG).f2,4.dpipe.out"
+awk -F ' [|] ' -v OFS=',' '{print $1, $3}' $LOG > "$OUT_DIR/$(basename $LOG).f1,3.dpipe.ocomma.out"
+awk '{print $3}' $BOOKS_TXT > "$OUT_DIR/$(basename $BOOKS_TXT).f3.w.out"
+awk '{print $1, $2}' $BOOKS_TXT > "$OUT_DIR/$(basename $BOOKS_TXT).f1-2.w.out"
+awk -F '[ ]*[,;][ ]*' '{print $1, $3}' $COLORS > "$OUT_DIR/$(basename $COLORS).f1,3.regex.out"
+
 for POS in 1 2 8 1-2 2-3 1-8; do
     cut -b $POS $TSV > "$OUT_DIR/$(basename $TSV).b${POS}.out"
     cut -b $POS $CSV > "$OUT_DIR/$(basename $CSV).b${POS}.out"
@@ -25,4 +59,91 @@ for POS in 1 2 8 1-2 2-3 1-8; do
     cut -c $POS $CSV > "$OUT_DIR/$(basename $CSV).c${POS}.out"
 done
 
-echo -e "AA\nÉÉ\nSS\nJJ" > "$OUT_DIR/books.c1,1.out"
\ No newline at end of file
+for POS in -3 8-; do
+    cut -b $POS $CSV > "$OUT_DIR/$(basename $CSV).b${POS}.out"
+done
+
+cut -b 2-3 --complement $CSV > "$OUT_DIR/$(basename $CSV).b2-3.complement.out"
+
+echo -e "AA\nÉÉ\nSS\nJJ" > "$OUT_DIR/books.c1,1.out"
+echo -e "A\nÉ\nS\nJ" > "$OUT_DIR/books.c1.out"
+
+# cut always selects in input order, --reorder keeps the listed order
+cut -f 3,1 $TSV > "$OUT_DIR/$(basename $TSV).f3,1.out"
+paste <(cut -f 3 $TSV) <(cut -f 1 $TSV) > "$OUT_DIR/$(basename $TSV).f3,1.reorder.out"
+cut -b 2-3,1-2 $TSV > "$OUT_DIR/$(basename $TSV).b2-3,1-2.out"
+# GNU cut has no header or CSV support, these are written by hand
+cat > "$OUT_DIR/books.csv.nTitle,Author.out" <<'END'
+Title,Author
+La Confession de Claude,Émile Zola
+Waiting for Godot,Samuel Beckett
+"20,000 Leagues Under the Sea",Jules Verne
+END
+
+# Without --reorder, named fields come out in input order
+cat > "$OUT_DIR/books.csv.nTitle,Author.input-order.out" <<'END'
+Author,Title
+Émile Zola,La Confession de Claude
+Samuel Beckett,Waiting for Godot
+Jules Verne,"20,000 Leagues Under the Sea"
+END
+
+cut -f 1-2 $BOOKS > "$OUT_DIR/books.tsv.nAuthor-Year.out"
+
+cat > "$OUT_DIR/books.csv.nYear.complement.out" <<'END'
+Author,Title
+Émile Zola,La Confession de Claude
+Samuel Beckett,Waiting for Godot
+Jules Verne,"20,000 Leagues Under the Sea"
+END
+
+cat > "$OUT_DIR/movies2.csv.f1.dcomma.never.out" <<'END'
+title
+The Blues Brothers
+Les Misérables
+To Sir, with Love
+END
+
+cat > "$OUT_DIR/movies2.csv.f1-2.dcomma.non-numeric.out" <<'END'
+"title","year"
+"The Blues Brothers",1980
+"Les Misérables",2012
+"To Sir, with Love",1967
+END
+
+# Graphemes, display columns and whole characters for $UNICODE
+
+cat > "$OUT_DIR/unicode.txt.c1-4.out" <<'END'
+Name
+José
+山田  
+👩‍💻 Ad
+END
+
+cat > "$OUT_DIR/unicode.txt.c4.codepoints.out" <<'END'
+e
+e
+ 
+ 
+END
+
+cat > "$OUT_DIR/unicode.txt.columns1-10.out" <<'END'
+Name      
+José      
+山田      
+👩‍💻 Ada    
+END
+
+cat > "$OUT_DIR/unicode.txt.columns11-.out" <<'END'
+City
+São Paulo
+東京
+London
+END
+
+cat > "$OUT_DIR/unicode.txt.b1-5.n.out" <<'END'
+Name 
+Jose
+山
+👩
+END
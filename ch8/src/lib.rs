@@ -1,14 +1,21 @@
-use clap::{Command, Arg};
+use clap::{Command, Arg, ArgAction};
 use std::{
+    borrow::Cow,
+    cell::RefCell,
     fs::File,
     error::Error,
-    io::{self, BufRead, BufReader},
+    io::{self, BufRead, BufReader, Read, Write},
     ops::Range,
     num::NonZeroUsize,
+    rc::Rc,
 };
-use regex::Regex;
-use csv::{StringRecord, ReaderBuilder, WriterBuilder};
+use regex::{bytes, Regex};
+use csv::{QuoteStyle, StringRecord, ReaderBuilder, WriterBuilder};
 use crate::Extract::*;
+use crate::Mode::*;
+use crate::Delimiter::*;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 type PositionList = Vec<Range<usize>>;
@@ -16,14 +23,46 @@ type PositionList = Vec<Range<usize>>;
 #[derive(Debug)]
 pub enum Extract {
     Fields(PositionList),
+    FieldNames(Vec<String>),
     Bytes(PositionList),
     Chars(PositionList),
+    Codepoints(PositionList),
+    Columns(PositionList),
+}
+
+#[derive(Debug)]
+pub enum Mode {
+    Plain,
+    Csv(CsvOptions),
+}
+
+#[derive(Debug)]
+pub enum Delimiter {
+    Literal(Vec<u8>),
+    Pattern(bytes::Regex),
+    Whitespace,
+}
+
+#[derive(Debug)]
+pub struct CsvOptions {
+    delimiter: u8,
+    quote: u8,
+    escape: Option<u8>,
+    flexible: bool,
+    quote_style: QuoteStyle,
 }
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
-    delimiter: u8,
+    mode: Mode,
+    delimiter: Delimiter,
+    output_delimiter: String,
+    only_delimited: bool,
+    complement: bool,
+    header: bool,
+    no_split: bool,
+    reorder: bool,
     extract: Extract,
 }
 
@@ -48,13 +87,110 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Field delimiter")
                 .default_value("\t"),
         )
+        .arg(
+            Arg::new("regex_delim")
+                .value_name("PATTERN")
+                .long("regex-delim")
+                .help("Split fields on matches of a regular expression")
+                .conflicts_with_all(["delimiter", "whitespace", "bytes", "chars"]),
+        )
+        .arg(
+            Arg::new("whitespace")
+                .short('w')
+                .long("whitespace")
+                .help("Split fields on runs of whitespace, like awk")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["delimiter", "bytes", "chars"]),
+        )
+        .arg(
+            Arg::new("mode")
+                .value_name("MODE")
+                .long("mode")
+                .help("How fields are split: plain bytes like POSIX cut, or RFC 4180 CSV \
+                       [default: csv for single-byte delimiters, otherwise plain]")
+                .value_parser(["plain", "csv"]),
+        )
+        .arg(
+            Arg::new("quote")
+                .value_name("CHAR")
+                .long("quote")
+                .help("CSV quote character [default: \"]"),
+        )
+        .arg(
+            Arg::new("escape")
+                .value_name("CHAR")
+                .long("escape")
+                .help("CSV escape character for quotes [default: doubled quotes]"),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .help("Fail on CSV records with a different number of fields")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("quote_style")
+                .value_name("STYLE")
+                .long("quote-style")
+                .help("When to quote CSV output fields [default: necessary]")
+                .value_parser(["necessary", "always", "never", "non-numeric"]),
+        )
+        .arg(
+            Arg::new("output_delimiter")
+                .value_name("STRING")
+                .long("output-delimiter")
+                .help("Output field delimiter [default: the input delimiter]"),
+        )
+        .arg(
+            Arg::new("only_delimited")
+                .short('s')
+                .long("only-delimited")
+                .help("Do not print lines without delimiters")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("complement")
+                .long("complement")
+                .help("Complement the set of selected fields, bytes or characters")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("preserve_order")
+                .long("preserve-order")
+                .help("Output each selected position once, in input order (default)")
+                .action(ArgAction::SetTrue)
+                .overrides_with("reorder"),
+        )
+        .arg(
+            Arg::new("reorder")
+                .long("reorder")
+                .help("Output positions in the order listed, repeating any given twice")
+                .action(ArgAction::SetTrue)
+                .overrides_with("preserve_order"),
+        )
         .arg(
             Arg::new("fields")
                 .value_name("FIELDS")
                 .short('f')
                 .long("fields")
                 .help("Selected field")
-                .conflicts_with_all(&["chars", "bytes"]),
+                .allow_hyphen_values(true)
+                .conflicts_with_all(["chars", "bytes", "columns"]),
+        )
+        .arg(
+            Arg::new("header")
+                .long("header")
+                .help("Treat the first record as a header naming the fields")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["chars", "bytes", "columns"]),
+        )
+        .arg(
+            Arg::new("field_names")
+                .value_name("NAMES")
+                .long("fields-by-name")
+                .help("Selected fields by header name, e.g. \"Author,Year\" or \"Author-Year\"")
+                .requires("header")
+                .conflicts_with_all(["fields", "chars", "bytes", "columns"]),
         )
         .arg(
             Arg::new("bytes")
@@ -62,7 +198,8 @@ pub fn get_args() -> MyResult<Config> {
                 .short('b')
                 .long("bytes")
                 .help("Selected bytes")
-                .conflicts_with_all(&["chars", "fields"]),
+                .allow_hyphen_values(true)
+                .conflicts_with_all(["chars", "fields", "columns"]),
         )
         .arg(
             Arg::new("chars")
@@ -70,7 +207,30 @@ pub fn get_args() -> MyResult<Config> {
                 .short('c')
                 .long("chars")
                 .help("Selected characters")
-                .conflicts_with_all(&["bytes", "fields"]),
+                .allow_hyphen_values(true)
+                .conflicts_with_all(["bytes", "fields", "columns"]),
+        )
+        .arg(
+            Arg::new("columns")
+                .value_name("COLUMNS")
+                .long("columns")
+                .help("Selected display columns")
+                .allow_hyphen_values(true)
+                .conflicts_with_all(["bytes", "fields", "chars"]),
+        )
+        .arg(
+            Arg::new("no_split")
+                .short('n')
+                .help("Do not split multibyte characters (with --bytes)")
+                .action(ArgAction::SetTrue)
+                .requires("bytes"),
+        )
+        .arg(
+            Arg::new("codepoints")
+                .long("codepoints")
+                .help("Count --chars as Unicode code points instead of graphemes")
+                .action(ArgAction::SetTrue)
+                .requires("chars"),
         )
         .get_matches();
 
@@ -79,85 +239,261 @@ pub fn get_args() -> MyResult<Config> {
         .cloned()
         .collect::<Vec<String>>();
 
-    let delimiter= matches.get_one::<String>("delimiter")
-        .map(String::from)
-        .expect("delimiter missing");
-    let delim_bytes = delimiter.as_bytes();
-    if delim_bytes.len() != 1 {
-        return Err(From::from(format!(
-            "--delim \"{}\" must be a single byte",
-            delimiter
-        )));
+    let delimiter = if let Some(pattern) = matches.get_one::<String>("regex_delim") {
+        Pattern(bytes::Regex::new(pattern)
+            .map_err(|_| format!("Invalid --regex-delim \"{}\"", pattern))?)
+    } else if matches.get_flag("whitespace") {
+        Whitespace
+    } else {
+        let delimiter = matches.get_one::<String>("delimiter")
+            .expect("delimiter missing");
+        if delimiter.is_empty() {
+            return Err(From::from("--delim \"\" must not be empty"));
+        }
+        Literal(delimiter.as_bytes().to_vec())
     };
 
+    let quote = matches.get_one::<String>("quote")
+        .map(|s| single_byte("--quote", s))
+        .transpose()?;
+
+    let escape = matches.get_one::<String>("escape")
+        .map(|s| single_byte("--escape", s))
+        .transpose()?;
+
+    let strict = matches.get_flag("strict");
+
+    let quote_style = matches.get_one::<String>("quote_style")
+        .map(|s| match s.as_str() {
+            "always" => QuoteStyle::Always,
+            "never" => QuoteStyle::Never,
+            "non-numeric" => QuoteStyle::NonNumeric,
+            _ => QuoteStyle::Necessary,
+        });
+
+    // The csv reader can only split on a single byte, anything else is
+    // split as plain text unless csv mode was asked for explicitly
+    let csv_delimiter = match &delimiter {
+        Literal(delim) if delim.len() == 1 => Some(delim[0]),
+        _ => None,
+    };
+    let mode = match (matches.get_one::<String>("mode").map(String::as_str), csv_delimiter) {
+        (Some("plain"), _) | (None, None) => {
+            if quote.is_some() || escape.is_some() || strict || quote_style.is_some() {
+                return Err(From::from(
+                    "--quote, --escape, --strict and --quote-style require --mode csv",
+                ));
+            }
+            Plain
+        }
+        (_, Some(delimiter)) => Csv(CsvOptions {
+            delimiter,
+            quote: quote.unwrap_or(b'"'),
+            escape,
+            flexible: !strict,
+            quote_style: quote_style.unwrap_or(QuoteStyle::Necessary),
+        }),
+        (_, None) => return Err(From::from("--mode csv requires a single-byte --delim")),
+    };
+
+    let output_delimiter = match (matches.get_one::<String>("output_delimiter"), &delimiter) {
+        (Some(output_delimiter), _) => output_delimiter.clone(),
+        (None, Literal(delim)) => String::from_utf8_lossy(delim).into_owned(),
+        (None, _) => " ".to_string(),
+    };
+
+    let reorder = matches.get_flag("reorder");
+
     let fields= matches.get_one::<String>("fields")
-        .map(|s| parse_pos(s))
+        .map(|s| parse_pos(s, reorder))
+        .transpose()?;
+
+    let field_names = matches.get_one::<String>("field_names")
+        .map(|s| parse_names(s))
         .transpose()?;
 
     let bytes= matches.get_one::<String>("bytes")
-        .map(|s| parse_pos(s))
+        .map(|s| parse_pos(s, reorder))
         .transpose()?;
 
     let chars = matches.get_one::<String>("chars")
-        .map(|s| parse_pos(s))
+        .map(|s| parse_pos(s, reorder))
+        .transpose()?;
+
+    let columns = matches.get_one::<String>("columns")
+        .map(|s| parse_pos(s, reorder))
         .transpose()?;
 
     let extract = if let Some(field_pos) = fields {
         Fields(field_pos)
+    } else if let Some(names) = field_names {
+        FieldNames(names)
     } else if let Some(byte_pos) = bytes {
         Bytes(byte_pos)
     } else if let Some(char_pos) = chars {
-        Chars(char_pos)
+        if matches.get_flag("codepoints") {
+            Codepoints(char_pos)
+        } else {
+            Chars(char_pos)
+        }
+    } else if let Some(column_pos) = columns {
+        Columns(column_pos)
     } else {
         // return Err(From::from("Must have --fields, --bytes, or --chars"));
         return Err(From::from("the following required arguments were not provided:
-  <--fields <FIELDS>|--bytes <BYTES>|--chars <CHARS>>"));
+  <--fields <FIELDS>|--fields-by-name <NAMES>|--bytes <BYTES>|--chars <CHARS>|--columns <COLUMNS>>"));
     };
 
     Ok(Config{
         files,
-        delimiter: *delim_bytes.first().expect("No delimiter"),
+        mode,
+        delimiter,
+        output_delimiter,
+        only_delimited: matches.get_flag("only_delimited"),
+        complement: matches.get_flag("complement"),
+        header: matches.get_flag("header"),
+        no_split: matches.get_flag("no_split"),
+        reorder,
         extract,
     })
 }
 
+fn single_byte(option: &str, value: &str) -> MyResult<u8> {
+    match value.as_bytes() {
+        [byte] => Ok(*byte),
+        _ => Err(From::from(format!(
+            "{} \"{}\" must be a single byte",
+            option, value
+        ))),
+    }
+}
+
 fn parse_index(input: &str) -> Result<usize, String> {
     let value_error = || format!("illegal list value: \"{}\"", input );
+    if input.starts_with('+') {
+        return Err(value_error());
+    }
     input
-        .starts_with('+')
-        .then(|| Err(value_error()))
-        .unwrap_or_else(|| {
-            input
-                .parse::<NonZeroUsize>()
-                .map(|n| usize::from(n) - 1)
-                .map_err(|_| value_error())
-        })
+        .parse::<NonZeroUsize>()
+        .map(|n| usize::from(n) - 1)
+        .map_err(|_| value_error())
 }
 
-fn parse_pos(range: &str) -> MyResult<PositionList> {
-    let range_re = Regex::new(r"^(\d+)-(\d+)$").unwrap();
+// Accepts "N", "N-M", "-M" (from the first position) and "N-" (to the end
+// of the line); an open end is represented by `usize::MAX`. Unless `reorder`
+// is set the ranges are normalized to input order, like POSIX cut.
+fn parse_pos(range: &str, reorder: bool) -> MyResult<PositionList> {
+    let range_re = Regex::new(r"^(\d*)-(\d*)$").unwrap();
     range
         .split(',')
-        .into_iter()
         .map(|val|{
             parse_index(val).map(|n| n..n+1).or_else(|e| {
-                range_re.captures(val).ok_or(e).and_then(|captures| {
-                    let n1 = parse_index(&captures[1])?;
-                    let n2 = parse_index(&captures[2])?;
+                range_re.captures(val).ok_or(e.clone()).and_then(|captures| {
+                    let (start, end) = (&captures[1], &captures[2]);
+                    if start.is_empty() && end.is_empty() {
+                        return Err(e);
+                    }
+                    let n1 = if start.is_empty() { 0 } else { parse_index(start)? };
+                    let n2 = if end.is_empty() { usize::MAX } else { parse_index(end)? + 1 };
                     if n1 >= n2 {
                         return Err(format!(
-                            "First number in range ({}) must be lower than second number ({})",
-                            n1 + 1, n2 + 1
+                            "First number in range ({}) must not be greater than second number ({})",
+                            n1 + 1, n2
                         ));
                     }
-                Ok(n1..n2 + 1)
+                Ok(n1..n2)
                 })
             })
         })
+        .collect::<Result<PositionList, _>>()
+        .map(|pos| if reorder { pos } else { normalize_pos(&pos) })
+        .map_err(From::from)
+}
+
+fn parse_names(names: &str) -> MyResult<Vec<String>> {
+    names
+        .split(',')
+        .map(|name| match name {
+            "" | "-" => Err(format!("illegal list value: \"{}\"", names)),
+            _ => Ok(name.to_string()),
+        })
         .collect::<Result<_, _>>()
         .map_err(From::from)
 }
 
+// Resolves field names, or "Start-End" name ranges with either side left
+// open, to positions in the header record.
+fn resolve_names(names: &[String], headers: &StringRecord, reorder: bool) -> MyResult<PositionList> {
+    let find = |name: &str| headers.iter().position(|header| header == name);
+    names
+        .iter()
+        .map(|name| {
+            if let Some(n) = find(name) {
+                return Ok(n..n + 1);
+            }
+            let (n1, n2) = name
+                .match_indices('-')
+                .find_map(|(i, _)| {
+                    let (start, end) = (&name[..i], &name[i + 1..]);
+                    let n1 = if start.is_empty() { Some(0) } else { find(start) };
+                    let n2 = if end.is_empty() { Some(usize::MAX) } else { find(end).map(|n| n + 1) };
+                    n1.zip(n2)
+                })
+                .ok_or_else(|| format!(
+                    "no field named \"{}\", available fields: {}",
+                    name,
+                    headers.iter().collect::<Vec<_>>().join(", ")
+                ))?;
+            if n1 >= n2 {
+                return Err(format!(
+                    "First field in range \"{}\" must not come after the second field",
+                    name
+                ));
+            }
+            Ok(n1..n2)
+        })
+        .collect::<Result<PositionList, _>>()
+        .map(|pos| if reorder { pos } else { normalize_pos(&pos) })
+        .map_err(From::from)
+}
+
+// Sorts the ranges and merges any that overlap or touch, so that each
+// position is selected once and in input order.
+fn normalize_pos(pos: &[Range<usize>]) -> PositionList {
+    let mut sorted = pos.to_vec();
+    sorted.sort_by_key(|range| range.start);
+
+    let mut merged: PositionList = vec![];
+    for range in sorted {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+// Returns the positions not covered by `pos`, in ascending order.
+fn complement_pos(pos: &[Range<usize>]) -> PositionList {
+    let mut gaps = vec![];
+    let mut next = 0;
+    for range in normalize_pos(pos) {
+        if range.start > next {
+            gaps.push(next..range.start);
+        }
+        next = range.end;
+    }
+    if next < usize::MAX {
+        gaps.push(next..usize::MAX);
+    }
+    gaps
+}
+
+// Limits a (possibly open-ended) range to the positions that exist.
+fn clamp(range: &Range<usize>, len: usize) -> Range<usize> {
+    range.start.min(len)..range.end.min(len)
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
@@ -166,36 +502,45 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    for filename in config.files {
-        match open(&filename) {
+    for filename in &config.files {
+        match open(filename) {
             Err(err) => eprintln!("{}: {}", filename, err),
             // Ok(_) => print!("Opened {}", filename),
             Ok(file) => match &config.extract {
-                Fields(field_pos) => {
-                    let mut reader = ReaderBuilder::new()
-                    .delimiter(config.delimiter)
-                        .has_headers(false)
-                        .from_reader(file);
-
-                    let mut wtr = WriterBuilder::new()
-                    .delimiter(config.delimiter)
-                    .from_writer(io::stdout());
-
-                    for record in reader.records() {
-                        let record = record?;
-                        wtr.write_record(extract_fields(&record, field_pos))?;
+                Fields(_) | FieldNames(_) => {
+                    match &config.mode {
+                        Plain => cut_plain(file, &config),
+                        Csv(options) => cut_csv(file, &config, options),
                     }
+                    .map_err(|e| format!("{}: {}", filename, e))?;
                 }
 
                 Bytes(byte_pos) => {
+                    let byte_pos = select(byte_pos, config.complement);
+                    let extract = if config.no_split { extract_whole_chars } else { extract_bytes };
                     for line in file.lines() {
-                        println!("{}", extract_bytes(&line?, byte_pos));
+                        println!("{}", extract(&line?, &byte_pos));
                     }
                 }
 
                 Chars(char_pos) => {
+                    let char_pos = select(char_pos, config.complement);
                     for line in file.lines() {
-                        println!("{}", extract_chars(&line?, char_pos));
+                        println!("{}", extract_chars(&line?, &char_pos));
+                    }
+                }
+
+                Codepoints(char_pos) => {
+                    let char_pos = select(char_pos, config.complement);
+                    for line in file.lines() {
+                        println!("{}", extract_codepoints(&line?, &char_pos));
+                    }
+                }
+
+                Columns(column_pos) => {
+                    let column_pos = select(column_pos, config.complement);
+                    for line in file.lines() {
+                        println!("{}", extract_columns(&line?, &column_pos));
                     }
                 }
             }
@@ -204,22 +549,276 @@ pub fn run(config: Config) -> MyResult<()> {
     Ok(())
 }
 
-fn extract_fields(record: &StringRecord, field_pos: &[Range<usize>]) -> Vec<String>{
+fn select(pos: &[Range<usize>], complement: bool) -> PositionList {
+    if complement {
+        complement_pos(pos)
+    } else {
+        pos.to_vec()
+    }
+}
+
+// Resolves the selected fields, by name against `headers` if needed.
+fn field_positions(config: &Config, headers: &StringRecord) -> MyResult<PositionList> {
+    let field_pos = match &config.extract {
+        FieldNames(names) => resolve_names(names, headers, config.reorder)?,
+        Fields(field_pos) => field_pos.clone(),
+        _ => unreachable!("no field selection"),
+    };
+    Ok(select(&field_pos, config.complement))
+}
+
+// Splits lines on the delimiter with no quoting, leaving the selected
+// bytes untouched; lines without a delimiter are passed through.
+fn cut_plain(file: Box<dyn BufRead>, config: &Config) -> MyResult<()> {
+    let mut lines = file.split(b'\n');
+    let header = if config.header { lines.next().transpose()? } else { None };
+    let headers = split_plain(header.as_deref().unwrap_or_default(), &config.delimiter)
+        .iter()
+        .map(|field| String::from_utf8_lossy(field).into_owned())
+        .collect::<StringRecord>();
+    let field_pos = field_positions(config, &headers)?;
+
+    let mut out = io::stdout().lock();
+    for line in header.into_iter().map(Ok).chain(lines) {
+        let line = line?;
+        let fields = split_plain(&line, &config.delimiter);
+        if fields.len() < 2 {
+            if !config.only_delimited {
+                out.write_all(&line)?;
+                out.write_all(b"\n")?;
+            }
+            continue;
+        }
+        out.write_all(&select_fields(&fields, &field_pos).join(config.output_delimiter.as_bytes()))?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn split_plain<'a>(line: &'a [u8], delimiter: &Delimiter) -> Vec<&'a [u8]> {
+    match delimiter {
+        Literal(delim) if delim.len() == 1 => line.split(|b| *b == delim[0]).collect(),
+        Literal(delim) => {
+            let mut fields = vec![];
+            let mut rest = line;
+            while let Some(i) = rest.windows(delim.len()).position(|w| w == delim.as_slice()) {
+                fields.push(&rest[..i]);
+                rest = &rest[i + delim.len()..];
+            }
+            fields.push(rest);
+            fields
+        }
+        Pattern(re) => re.split(line).collect(),
+        Whitespace => line
+            .split(u8::is_ascii_whitespace)
+            .filter(|field| !field.is_empty())
+            .collect(),
+    }
+}
+
+// Keeps what the csv reader reads from the input, so that lines can be
+// printed as they were
+struct Recorded<R> {
+    inner: R,
+    input: Rc<RefCell<Vec<u8>>>,
+}
+
+impl<R: Read> Read for Recorded<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes = self.inner.read(buf)?;
+        self.input.borrow_mut().extend(&buf[..bytes]);
+        Ok(bytes)
+    }
+}
+
+// What csv mode prints for a line: the selected fields, or the line as
+// it was when it has no delimiter
+enum Row<'a> {
+    Fields(Vec<String>),
+    Line(&'a [u8]),
+}
+
+fn cut_csv(file: Box<dyn BufRead>, config: &Config, options: &CsvOptions) -> MyResult<()> {
+    let input = Rc::new(RefCell::new(vec![]));
+    let mut reader = ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .has_headers(config.header)
+        .quote(options.quote)
+        .escape(options.escape)
+        .double_quote(options.escape.is_none())
+        .flexible(options.flexible)
+        .from_reader(Recorded { inner: file, input: Rc::clone(&input) });
+
+    let headers = if config.header { reader.headers()?.clone() } else { StringRecord::new() };
+    let field_pos = field_positions(config, &headers)?;
+
+    // The csv writer only handles single-byte delimiters,
+    // longer ones are joined as plain text
+    let mut wtr = match config.output_delimiter.as_bytes() {
+        [byte] => {
+            let mut builder = WriterBuilder::new();
+            builder
+                .delimiter(*byte)
+                .flexible(true)
+                .quote(options.quote)
+                .quote_style(options.quote_style);
+            if let Some(escape) = options.escape {
+                builder.escape(escape).double_quote(false);
+            }
+            Some(builder.from_writer(io::stdout()))
+        }
+        _ => None,
+    };
+    let mut write = |row: Row| -> MyResult<()> {
+        match (row, wtr.as_mut()) {
+            (Row::Fields(fields), Some(wtr)) => wtr.write_record(&fields)?,
+            (Row::Fields(fields), None) => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|field| quote_field(field, &config.output_delimiter, options))
+                    .collect();
+                println!("{}", fields.join(&config.output_delimiter));
+            }
+            (Row::Line(line), wtr) => {
+                if let Some(wtr) = wtr {
+                    wtr.flush()?;
+                }
+                let mut out = io::stdout().lock();
+                out.write_all(line)?;
+                out.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    };
+
+    if config.header {
+        write(Row::Fields(extract_fields(&headers, &field_pos)))?;
+    }
+    let mut consumed = reader.position().byte();
+    input.borrow_mut().drain(..consumed as usize);
+    let mut record = StringRecord::new();
+    let mut after_cr = false;
+    loop {
+        let more = reader.read_record(&mut record)?;
+        let end = reader.position().byte();
+        let text: Vec<u8> = input.borrow_mut().drain(..(end - consumed) as usize).collect();
+        consumed = end;
+
+        // A record ending in "\r" leaves the "\n" after it to the next one,
+        // and the reader skips blank lines, which come before the record
+        let text = match text.strip_prefix(b"\n") {
+            Some(rest) if after_cr => rest,
+            _ => &text[..],
+        };
+        after_cr = text.ends_with(b"\r");
+        let blank = text.iter().take_while(|b| matches!(b, b'\r' | b'\n')).count();
+        if !config.only_delimited {
+            for line in text[..blank].split_inclusive(|b| *b == b'\n') {
+                write(Row::Line(line.strip_suffix(b"\n").unwrap_or(line)))?;
+            }
+        }
+        if !more {
+            break;
+        }
+        if record.len() < 2 {
+            if !config.only_delimited {
+                let line = &text[blank..];
+                write(Row::Line(line.strip_suffix(b"\n").unwrap_or(line)))?;
+            }
+            continue;
+        }
+        write(Row::Fields(extract_fields(&record, &field_pos)))?;
+    }
+    Ok(())
+}
+
+// Quotes a field joined with a multi-byte delimiter the way the csv writer
+// does for a single byte one
+fn quote_field<'a>(field: &'a str, delimiter: &str, options: &CsvOptions) -> Cow<'a, str> {
+    let quote = char::from(options.quote);
+    let escape = options.escape.map(char::from);
+    let quoted = match options.quote_style {
+        QuoteStyle::Always => true,
+        QuoteStyle::Never => false,
+        QuoteStyle::NonNumeric => field.parse::<f64>().is_err() && field.parse::<i128>().is_err(),
+        _ => {
+            field.contains(delimiter)
+                || field.contains([quote, '\r', '\n'])
+                || escape.is_some_and(|escape| field.contains(escape))
+        }
+    };
+    if !quoted {
+        return Cow::Borrowed(field);
+    }
+    let escaped = field.replace(quote, &format!("{}{}", escape.unwrap_or(quote), quote));
+    Cow::Owned(format!("{}{}{}", quote, escaped, quote))
+}
+
+fn select_fields<T: Clone>(fields: &[T], field_pos: &[Range<usize>]) -> Vec<T> {
     field_pos
         .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| record.get(i)))
+        .flat_map(|range| fields[clamp(range, fields.len())].iter().cloned())
+        .collect()
+}
+
+fn extract_fields(record: &StringRecord, field_pos: &[Range<usize>]) -> Vec<String>{
+    let fields: Vec<_> = record.iter().collect();
+    select_fields(&fields, field_pos)
+        .into_iter()
         .map(String::from)
         .collect()
 }
 
+// Selects the pieces of a line that span the given positions, keeping each
+// piece whole: like `cut -n`, a piece is selected when its last position is.
+fn extract_units(units: &[(Range<usize>, &str)], pos: &[Range<usize>]) -> String {
+    pos.iter()
+        .flat_map(|range| {
+            units
+                .iter()
+                .filter(|(span, _)| range.contains(&(span.end.max(span.start + 1) - 1)))
+                .map(|(_, unit)| *unit)
+        })
+        .collect()
+}
+
 fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
+    let graphemes: Vec<_> = line
+        .graphemes(true)
+        .enumerate()
+        .map(|(i, grapheme)| (i..i + 1, grapheme))
+        .collect();
+    extract_units(&graphemes, char_pos)
+}
+
+fn extract_columns(line: &str, column_pos: &[Range<usize>]) -> String {
+    let mut column = 0;
+    let graphemes: Vec<_> = line
+        .graphemes(true)
+        .map(|grapheme| {
+            let start = column;
+            column += grapheme.width();
+            (start..column, grapheme)
+        })
+        .collect();
+    extract_units(&graphemes, column_pos)
+}
+
+fn extract_whole_chars(line: &str, byte_pos: &[Range<usize>]) -> String {
+    let chars: Vec<_> = line
+        .char_indices()
+        .map(|(i, c)| (i..i + c.len_utf8(), &line[i..i + c.len_utf8()]))
+        .collect();
+    extract_units(&chars, byte_pos)
+}
+
+fn extract_codepoints(line: &str, char_pos: &[Range<usize>]) -> String {
     let chars: Vec<_> = line.chars().collect();
 
     char_pos
         .iter()
         .cloned()
-        .flat_map(|range| range.filter_map(|i| chars.get(i)))
+        .flat_map(|range| clamp(&range, chars.len()).filter_map(|i| chars.get(i)))
         .collect()
 }
 
@@ -228,7 +827,7 @@ fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
     let selected: Vec<_> = byte_pos
         .iter()
         .cloned()
-        .flat_map(|range| range.filter_map(|i| bytes.get(i).copied()))
+        .flat_map(|range| clamp(&range, bytes.len()).filter_map(|i| bytes.get(i).copied()))
         .collect();
 
     String::from_utf8_lossy(&selected).into_owned()
@@ -239,118 +838,271 @@ fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
 // cargo test -p ch8-cutr --lib
 
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
 mod unit_tests {
     use super::*;
 
     #[test]
     fn test_parse_pos() {
-        assert!(parse_pos("").is_err());
+        assert!(parse_pos("", true).is_err());
 
         // Zero is error
-        let res = parse_pos("0");
+        let res = parse_pos("0", true);
         assert!(res.is_err());
         assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"0\"");
 
-        let res = parse_pos("0-1");
+        let res = parse_pos("0-1", true);
         assert!(res.is_err());
         assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"0\"");
 
         // A leading "+" is an error
-        let res = parse_pos("+1");
+        let res = parse_pos("+1", true);
         assert!(res.is_err());
         assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"+1\"");
 
-        let res = parse_pos("+1-2");
+        let res = parse_pos("+1-2", true);
         assert!(res.is_err());
         assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"+1-2\"");
 
-        let res = parse_pos("1-+2");
+        let res = parse_pos("1-+2", true);
         assert!(res.is_err());
         assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"1-+2\"");
 
         // Any non-number is an error
-        let res = parse_pos("a");
+        let res = parse_pos("a", true);
         assert!(res.is_err());
         assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"a\"");
 
-        let res = parse_pos("1,a");
+        let res = parse_pos("1,a", true);
         assert!(res.is_err());
         assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"a\"");
 
-        let res = parse_pos("1-a");
+        let res = parse_pos("1-a", true);
         assert!(res.is_err());
         assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"1-a\"");
 
-        let res = parse_pos("a-1");
+        let res = parse_pos("a-1", true);
         assert!(res.is_err());
         assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"a-1\"");
 
         // Wonky ranges
-        let res = parse_pos("-");
+        let res = parse_pos("-", true);
         assert!(res.is_err());
 
-        let res = parse_pos(",");
+        let res = parse_pos(",", true);
         assert!(res.is_err());
 
-        let res = parse_pos("1,");
+        let res = parse_pos("1,", true);
         assert!(res.is_err());
 
-        let res = parse_pos("1-");
+        let res = parse_pos("1-1-1", true);
         assert!(res.is_err());
 
-        let res = parse_pos("1-1-1");
+        let res = parse_pos("1-1-a", true);
         assert!(res.is_err());
 
-        let res = parse_pos("1-1-a");
+        let res = parse_pos("--1", true);
         assert!(res.is_err());
 
-        // First number must be less than second
-        let res = parse_pos("1-1");
+        let res = parse_pos("1--", true);
         assert!(res.is_err());
-        assert_eq!(
-            res.unwrap_err().to_string(),
-            "First number in range (1) must be lower than second number (1)"
-        );
 
-        let res = parse_pos("2-1");
+        // First number must not be greater than second
+        let res = parse_pos("2-1", true);
         assert!(res.is_err());
         assert_eq!(
             res.unwrap_err().to_string(),
-            "First number in range (2) must be lower than second number (1)"
+            "First number in range (2) must not be greater than second number (1)"
         );
 
         // All followings are acceptable
-        let res = parse_pos("1");
+        let res = parse_pos("1", true);
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![0..1]);
 
-        let res = parse_pos("01");
+        let res = parse_pos("01", true);
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![0..1]);
 
-        let res = parse_pos("1,3");
+        let res = parse_pos("1,3", true);
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![0..1, 2..3]);
 
-        let res = parse_pos("001,003");
+        let res = parse_pos("001,003", true);
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![0..1, 2..3]);
 
-        let res = parse_pos("1-3");
+        let res = parse_pos("1-3", true);
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![0..3]);
 
-        let res = parse_pos("0001-03");
+        let res = parse_pos("0001-03", true);
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![0..3]);
 
-        let res = parse_pos("1,7,3-5");
+        let res = parse_pos("1,7,3-5", true);
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![0..1, 6..7, 2..5]);
 
-        let res = parse_pos("15,19-20");
+        let res = parse_pos("15,19-20", true);
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![14..15, 18..20]);
+
+        // Single-element and open-ended ranges
+        let res = parse_pos("3-3", true);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![2..3]);
+
+        let res = parse_pos("-3", true);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..3]);
+
+        let res = parse_pos("5-", true);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![4..usize::MAX]);
+
+        let res = parse_pos("-2,4-", true);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..2, 3..usize::MAX]);
+    }
+
+    #[test]
+    fn test_parse_pos_preserve_order() {
+        let res = parse_pos("1,7,3-5", false);
+        assert_eq!(res.unwrap(), vec![0..1, 2..5, 6..7]);
+
+        let res = parse_pos("1,1", false);
+        assert_eq!(res.unwrap(), vec![0..1]);
+
+        let res = parse_pos("3-5,1-3", false);
+        assert_eq!(res.unwrap(), vec![0..5]);
+
+        // Adjacent ranges are merged as well
+        let res = parse_pos("4-,1-3", false);
+        assert_eq!(res.unwrap(), vec![0..usize::MAX]);
+
+        let res = parse_pos("5,1,8-9", false);
+        assert_eq!(res.unwrap(), vec![0..1, 4..5, 7..9]);
+
+        assert!(parse_pos("2-1", false).is_err());
+    }
+
+    #[test]
+    fn test_resolve_names() {
+        let headers = StringRecord::from(vec!["Author", "Year", "Title", "Sub-Title"]);
+        let names = |s: &str| parse_names(s).unwrap();
+
+        let res = resolve_names(&names("Title,Author"), &headers, true);
+        assert_eq!(res.unwrap(), vec![2..3, 0..1]);
+
+        let res = resolve_names(&names("Title,Author"), &headers, false);
+        assert_eq!(res.unwrap(), vec![0..1, 2..3]);
+
+        let res = resolve_names(&names("Author-Year"), &headers, true);
+        assert_eq!(res.unwrap(), vec![0..2]);
+
+        let res = resolve_names(&names("Year-,-Author"), &headers, true);
+        assert_eq!(res.unwrap(), vec![1..usize::MAX, 0..1]);
+
+        let res = resolve_names(&names("Year-,-Author"), &headers, false);
+        assert_eq!(res.unwrap(), vec![0..usize::MAX]);
+
+        // Names containing a dash are matched as a whole first
+        let res = resolve_names(&names("Sub-Title,Title-Sub-Title"), &headers, true);
+        assert_eq!(res.unwrap(), vec![3..4, 2..4]);
+
+        let res = resolve_names(&names("Publisher"), &headers, true);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "no field named \"Publisher\", available fields: Author, Year, Title, Sub-Title"
+        );
+
+        let res = resolve_names(&names("Title-Author"), &headers, true);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "First field in range \"Title-Author\" must not come after the second field"
+        );
+
+        assert!(parse_names("Author,").is_err());
+        assert!(parse_names("-").is_err());
+    }
+
+    #[test]
+    fn test_split_plain() {
+        let literal = |s: &str| Literal(s.as_bytes().to_vec());
+        let pattern = |s: &str| Pattern(bytes::Regex::new(s).unwrap());
+        let strs = |fields: Vec<&[u8]>| -> Vec<String> {
+            fields.iter().map(|f| String::from_utf8_lossy(f).into_owned()).collect()
+        };
+
+        assert_eq!(strs(split_plain(b"a\tb\t\tc", &literal("\t"))), ["a", "b", "", "c"]);
+        assert_eq!(strs(split_plain(b"a | b | c", &literal(" | "))), ["a", "b", "c"]);
+        assert_eq!(strs(split_plain(b"a | b | ", &literal(" | "))), ["a", "b", ""]);
+        assert_eq!(strs(split_plain(b"abc", &literal(" | "))), ["abc"]);
+        assert_eq!(strs(split_plain(b"a1b22c", &pattern("[0-9]+"))), ["a", "b", "c"]);
+        assert_eq!(strs(split_plain(b"  a \t b  c ", &Whitespace)), ["a", "b", "c"]);
+        assert!(split_plain(b"   ", &Whitespace).is_empty());
+    }
+
+    #[test]
+    fn test_extract_chars() {
+        assert_eq!(extract_chars("", &[0..1]), "");
+        assert_eq!(extract_chars("ábc", &[0..1]), "á");
+        assert_eq!(extract_chars("ábc", &[0..1, 2..3]), "ác");
+        assert_eq!(extract_chars("ábc", &[0..3]), "ábc");
+        assert_eq!(extract_chars("ábc", &[2..3, 1..2]), "cb");
+        assert_eq!(extract_chars("ábc", &[1..usize::MAX]), "bc");
+
+        // Combining marks and ZWJ sequences are a single character
+        assert_eq!(extract_chars("e\u{301}x", &[0..1]), "e\u{301}");
+        assert_eq!(extract_chars("a\u{1F468}\u{200D}\u{1F469}b", &[1..2]), "\u{1F468}\u{200D}\u{1F469}");
+        assert_eq!(extract_codepoints("e\u{301}x", &[0..1]), "e");
+    }
+
+    #[test]
+    fn test_extract_columns() {
+        assert_eq!(extract_columns("ab日本cd", &[0..2]), "ab");
+        assert_eq!(extract_columns("ab日本cd", &[2..4]), "日");
+        // A wide character is only selected if its last column is
+        assert_eq!(extract_columns("ab日本cd", &[0..3]), "ab");
+        assert_eq!(extract_columns("ab日本cd", &[3..6]), "日本");
+        assert_eq!(extract_columns("ab日本cd", &[6..usize::MAX]), "cd");
+        assert_eq!(extract_columns("e\u{301}x", &[0..1]), "e\u{301}");
+    }
+
+    #[test]
+    fn test_extract_bytes() {
+        assert_eq!(extract_bytes("ábc", &[0..1]), "\u{FFFD}");
+        assert_eq!(extract_bytes("ábc", &[0..2]), "á");
+        assert_eq!(extract_bytes("ábc", &[0..3]), "áb");
+        assert_eq!(extract_whole_chars("ábc", &[0..1]), "");
+        assert_eq!(extract_whole_chars("ábc", &[0..2]), "á");
+        assert_eq!(extract_whole_chars("ábc", &[1..3]), "áb");
+        assert_eq!(extract_whole_chars("ábc", &[2..3]), "b");
+    }
+
+    #[test]
+    fn test_normalize_pos() {
+        assert!(normalize_pos(&[]).is_empty());
+        assert_eq!(normalize_pos(&[2..3, 0..1]), vec![0..1, 2..3]);
+        assert_eq!(normalize_pos(&[0..3, 1..2]), vec![0..3]);
+        assert_eq!(normalize_pos(&[0..2, 2..4, 5..6]), vec![0..4, 5..6]);
+    }
+
+    #[test]
+    fn test_extract_chars_preserve_order() {
+        let pos = parse_pos("3,1,1-2", false).unwrap();
+        assert_eq!(extract_chars("ábc", &pos), "ábc");
+        assert_eq!(extract_bytes("abc", &pos), "abc");
+    }
+
+    #[test]
+    fn test_complement_pos() {
+        assert_eq!(complement_pos(&[0..1]), vec![1..usize::MAX]);
+        assert_eq!(complement_pos(&[2..5]), vec![0..2, 5..usize::MAX]);
+        assert_eq!(complement_pos(&[6..7, 0..1, 2..5]), vec![1..2, 5..6, 7..usize::MAX]);
+        assert_eq!(complement_pos(&[0..3, 1..2]), vec![3..usize::MAX]);
+        assert!(complement_pos(&[0..usize::MAX]).is_empty());
     }
 
     #[test]
@@ -361,5 +1113,6 @@ mod unit_tests {
         assert_eq!(extract_fields(&rec, &[0..1, 2..3]), &["Captain", "12345"]);
         assert_eq!(extract_fields(&rec, &[0..1, 3..4]), &["Captain"]);
         assert_eq!(extract_fields(&rec, &[1..2, 0..1]), &["Sham", "Captain"]);
+        assert_eq!(extract_fields(&rec, &[1..usize::MAX]), &["Sham", "12345"]);
     }
 }
\ No newline at end of file
@@ -8,6 +8,14 @@ const PRG: &str = "ch8-cutr";
 const CSV: &str = "tests/inputs/movies1.csv";
 const TSV: &str = "tests/inputs/movies1.tsv";
 const BOOKS: &str = "tests/inputs/books.tsv";
+const BOOKS_CSV: &str = "tests/inputs/books.csv";
+const MIXED: &str = "tests/inputs/mixed.tsv";
+const QUOTES: &str = "tests/inputs/quotes.tsv";
+const MOVIES2: &str = "tests/inputs/movies2.csv";
+const BOOKS_TXT: &str = "tests/inputs/books.txt";
+const LOG: &str = "tests/inputs/app.log";
+const COLORS: &str = "tests/inputs/colors.txt";
+const UNICODE: &str = "tests/inputs/unicode.txt";
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -59,7 +67,7 @@ fn dies_not_enough_args() -> TestResult {
     dies(
         &[CSV],
         "the following required arguments were not provided:\n  \
-        <--fields <FIELDS>|--bytes <BYTES>|--chars <CHARS>>",
+        <--fields <FIELDS>|--fields-by-name <NAMES>|--bytes <BYTES>|--chars <CHARS>|--columns <COLUMNS>>",
     )
 }
 
@@ -98,7 +106,7 @@ fn dies_bad_digit_chars() -> TestResult {
 fn dies_empty_delimiter() -> TestResult {
     dies(
         &[CSV, "-f", "1", "-d", ""],
-        r#"--delim "" must be a single byte"#,
+        r#"--delim "" must not be empty"#,
     )
 }
 
@@ -106,11 +114,30 @@ fn dies_empty_delimiter() -> TestResult {
 #[test]
 fn dies_bad_delimiter() -> TestResult {
     dies(
-        &[CSV, "-f", "1", "-d", ",,"],
-        r#"--delim ",," must be a single byte"#,
+        &[CSV, "-f", "1", "-d", ",,", "--mode", "csv"],
+        "--mode csv requires a single-byte --delim",
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_bad_regex_delimiter() -> TestResult {
+    dies(
+        &[CSV, "-f", "1", "--regex-delim", "("],
+        r#"Invalid --regex-delim "(""#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_whitespace_bytes() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([CSV, "-w", "-b", "1"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn dies_chars_bytes_fields() -> TestResult {
@@ -151,6 +178,51 @@ fn dies_chars_bytes() -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_field_names_without_header() -> TestResult {
+    dies(
+        &[BOOKS_CSV, "--fields-by-name", "Author"],
+        "the following required arguments were not provided:\n  --header",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unknown_field_name() -> TestResult {
+    dies(
+        &[BOOKS_CSV, "-d", ",", "--header", "--fields-by-name", "Author,Publisher"],
+        r#"tests/inputs/books.csv: no field named "Publisher", available fields: Author, Year, Title"#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_csv_options_in_plain_mode() -> TestResult {
+    dies(
+        &[TSV, "-f", "1", "--mode", "plain", "--quote", "'"],
+        "--quote, --escape, --strict and --quote-style require --mode csv",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_quote() -> TestResult {
+    dies(
+        &[CSV, "-f", "1", "--quote", "''"],
+        r#"--quote "''" must be a single byte"#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_strict_ragged_records() -> TestResult {
+    dies(
+        &[MIXED, "-f", "1", "--strict"],
+        "tests/inputs/mixed.tsv: CSV error",
+    )
+}
+
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> TestResult {
     let expected = fs::read_to_string(expected_file)?;
@@ -264,6 +336,269 @@ fn csv_f1_3() -> TestResult {
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn tsv_f2_2() -> TestResult {
+    run(&[TSV, "-f", "2-2"], "tests/expected/movies1.tsv.f2-2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f_to_2() -> TestResult {
+    run(&[TSV, "-f", "-2"], "tests/expected/movies1.tsv.f-2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f2_to_end() -> TestResult {
+    run(&[TSV, "-f", "2-"], "tests/expected/movies1.tsv.f2-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_f2_2() -> TestResult {
+    run(
+        &[CSV, "-f", "2-2", "-d", ","],
+        "tests/expected/movies1.csv.f2-2.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_f_to_2() -> TestResult {
+    run(
+        &[CSV, "-f", "-2", "-d", ","],
+        "tests/expected/movies1.csv.f-2.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_f2_to_end() -> TestResult {
+    run(
+        &[CSV, "-f", "2-", "-d", ","],
+        "tests/expected/movies1.csv.f2-.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f1_complement() -> TestResult {
+    run(
+        &[TSV, "-f", "1", "--complement"],
+        "tests/expected/movies1.tsv.f1.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f2_complement() -> TestResult {
+    run(
+        &[TSV, "-f", "2", "--complement"],
+        "tests/expected/movies1.tsv.f2.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f1_3_complement() -> TestResult {
+    run(
+        &[TSV, "-f", "1,3", "--complement"],
+        "tests/expected/movies1.tsv.f1,3.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_output_delimiter() -> TestResult {
+    run(
+        &[TSV, "-f", "1,3", "--output-delimiter", " | "],
+        "tests/expected/movies1.tsv.f1,3.dpipe.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_output_delimiter() -> TestResult {
+    run(
+        &[CSV, "-f", "1,3", "-d", ",", "--output-delimiter", ";"],
+        "tests/expected/movies1.csv.f1,3.dsemi.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn lines_without_delimiter() -> TestResult {
+    // Without -s, lines without the delimiter and blank lines pass through
+    run(&[MIXED, "-f", "2"], "tests/expected/mixed.tsv.f2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_long_output_delimiter_quotes() -> TestResult {
+    // Fields with the output delimiter, a quote or a newline are quoted
+    Command::cargo_bin(PRG)?
+        .args(["-d", ",", "-f", "1,2", "--output-delimiter", " | "])
+        .write_stdin("a | b,c\n\"x\"\"y\",1\n\"two\nlines\",z\n")
+        .assert()
+        .success()
+        .stdout("\"a | b\" | c\n\"x\"\"y\" | 1\n\"two\nlines\" | z\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn only_delimited() -> TestResult {
+    run(&[MIXED, "-f", "2", "-s"], "tests/expected/mixed.tsv.f2.s.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_field_names() -> TestResult {
+    run(
+        &[BOOKS_CSV, "-d", ",", "--header", "--fields-by-name", "Title,Author", "--reorder"],
+        "tests/expected/books.csv.nTitle,Author.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_field_names_preserve_order() -> TestResult {
+    run(
+        &[BOOKS_CSV, "-d", ",", "--header", "--fields-by-name", "Title,Author"],
+        "tests/expected/books.csv.nTitle,Author.input-order.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_field_name_range() -> TestResult {
+    run(
+        &[BOOKS, "--header", "--fields-by-name", "Author-Year"],
+        "tests/expected/books.tsv.nAuthor-Year.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_field_name_complement() -> TestResult {
+    run(
+        &[BOOKS_CSV, "-d", ",", "--header", "--fields-by-name", "Year", "--complement"],
+        "tests/expected/books.csv.nYear.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn default_mode_matches_plain() -> TestResult {
+    // Without quotes in the input, csv mode cuts as plain mode does
+    let cut = |args: &[&str], stdin: &str| -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let output = Command::cargo_bin(PRG)?.args(args).write_stdin(stdin).output()?;
+        assert!(output.status.success());
+        Ok(output.stdout)
+    };
+    let mixed = fs::read_to_string(MIXED)?;
+    for fields in ["1", "2", "2-", "1,3"] {
+        assert_eq!(cut(&["-f", fields], &mixed)?, cut(&["-f", fields, "--mode", "plain"], &mixed)?);
+    }
+    let lines = "a,b\nnodelim\n\nc,d\n";
+    assert_eq!(cut(&["-d", ",", "-f", "2"], lines)?, cut(&["-d", ",", "-f", "2", "--mode", "plain"], lines)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn plain_f2() -> TestResult {
+    run(
+        &[QUOTES, "-f", "2", "--mode", "plain"],
+        "tests/expected/quotes.tsv.f2.plain.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn plain_f3() -> TestResult {
+    run(
+        &[QUOTES, "-f", "3", "--mode", "plain"],
+        "tests/expected/quotes.tsv.f3.plain.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn plain_f2_to_end() -> TestResult {
+    run(
+        &[QUOTES, "-f", "2-", "--mode", "plain"],
+        "tests/expected/quotes.tsv.f2-.plain.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn plain_csv_f1_3() -> TestResult {
+    run(
+        &[MOVIES2, "-f", "1,3", "-d", ",", "--mode", "plain"],
+        "tests/expected/movies2.csv.f1,3.dcomma.plain.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_quote_style_never() -> TestResult {
+    run(
+        &[MOVIES2, "-f", "1", "-d", ",", "--quote-style", "never"],
+        "tests/expected/movies2.csv.f1.dcomma.never.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_quote_style_non_numeric() -> TestResult {
+    run(
+        &[MOVIES2, "-f", "1-2", "-d", ",", "--quote-style", "non-numeric"],
+        "tests/expected/movies2.csv.f1-2.dcomma.non-numeric.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn string_delimiter() -> TestResult {
+    run(
+        &[LOG, "-f", "2,4", "-d", " | "],
+        "tests/expected/app.log.f2,4.dpipe.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn string_delimiter_output_delimiter() -> TestResult {
+    run(
+        &[LOG, "-f", "1,3", "-d", " | ", "--output-delimiter", ","],
+        "tests/expected/app.log.f1,3.dpipe.ocomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn whitespace_f3() -> TestResult {
+    run(&[BOOKS_TXT, "-w", "-f", "3"], "tests/expected/books.txt.f3.w.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn whitespace_f1_2() -> TestResult {
+    run(&[BOOKS_TXT, "-w", "-f", "1-2"], "tests/expected/books.txt.f1-2.w.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_delimiter() -> TestResult {
+    run(
+        &[COLORS, "--regex-delim", r"\s*[,;]\s*", "-f", "1,3"],
+        "tests/expected/colors.txt.f1,3.regex.out",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 fn tsv_b1() -> TestResult {
@@ -300,6 +635,27 @@ fn tsv_b1_8() -> TestResult {
     run_lossy(&[TSV, "-b", "1-8"], "tests/expected/movies1.tsv.b1-8.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn csv_b_to_3() -> TestResult {
+    run(&[CSV, "-b", "-3"], "tests/expected/movies1.csv.b-3.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_b8_to_end() -> TestResult {
+    run(&[CSV, "-b", "8-"], "tests/expected/movies1.csv.b8-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_b2_3_complement() -> TestResult {
+    run(
+        &[CSV, "-b", "2-3", "--complement"],
+        "tests/expected/movies1.csv.b2-3.complement.out",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 fn tsv_c1() -> TestResult {
@@ -336,8 +692,80 @@ fn tsv_c1_8() -> TestResult {
     run(&[TSV, "-c", "1-8"], "tests/expected/movies1.tsv.c1-8.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn grapheme_c1_4() -> TestResult {
+    run(&[UNICODE, "-c", "1-4"], "tests/expected/unicode.txt.c1-4.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn codepoint_c4() -> TestResult {
+    run(
+        &[UNICODE, "-c", "4", "--codepoints"],
+        "tests/expected/unicode.txt.c4.codepoints.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn columns_1_10() -> TestResult {
+    run(
+        &[UNICODE, "--columns", "1-10"],
+        "tests/expected/unicode.txt.columns1-10.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn columns_11_to_end() -> TestResult {
+    run(
+        &[UNICODE, "--columns", "11-"],
+        "tests/expected/unicode.txt.columns11-.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_no_split() -> TestResult {
+    run(&[UNICODE, "-b", "1-5", "-n"], "tests/expected/unicode.txt.b1-5.n.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn repeated_value() -> TestResult {
-    run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
+    run(&[BOOKS, "-c", "1,1", "--reorder"], "tests/expected/books.c1,1.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn repeated_value_preserve_order() -> TestResult {
+    run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f3_1() -> TestResult {
+    run(&[TSV, "-f", "3,1"], "tests/expected/movies1.tsv.f3,1.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f3_1_reorder() -> TestResult {
+    run(&[TSV, "-f", "3,1", "--reorder"], "tests/expected/movies1.tsv.f3,1.reorder.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_b_overlapping() -> TestResult {
+    run(&[TSV, "-b", "2-3,1-2"], "tests/expected/movies1.tsv.b2-3,1-2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_preserve_order_overrides_reorder() -> TestResult {
+    run(
+        &[TSV, "-f", "3,1", "--reorder", "--preserve-order"],
+        "tests/expected/movies1.tsv.f3,1.out",
+    )
 }
\ No newline at end of file
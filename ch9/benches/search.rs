@@ -0,0 +1,58 @@
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use regex::Regex;
+use std::hint::black_box;
+use std::io::{BufRead, Cursor};
+
+// About 16 MB of log lines, one in a thousand with an error
+fn make_log() -> Vec<u8> {
+    let mut log = String::new();
+    for i in 0..200_000 {
+        let level = if i % 1000 == 0 { "ERROR" } else { "INFO" };
+        log += &format!(
+            "2024-05-01T12:{:02}:{:02} {level} worker-{} handled request {i} in {}ms\n",
+            i / 60 % 60,
+            i % 60,
+            i % 8,
+            i % 250
+        );
+    }
+    log.into_bytes()
+}
+
+// How lines were searched before: one String and one regex call per line
+fn count_per_line(pattern: &Regex, mut input: impl BufRead) -> usize {
+    let mut count = 0;
+    let mut line = String::new();
+    while input.read_line(&mut line).unwrap() > 0 {
+        if pattern.is_match(line.trim_end_matches('\n')) {
+            count += 1;
+        }
+        line.clear();
+    }
+    count
+}
+
+fn search(c: &mut Criterion) {
+    let log = make_log();
+    let mut group = c.benchmark_group("search");
+    group.throughput(Throughput::Bytes(log.len() as u64));
+    group.sample_size(20);
+
+    for (name, pattern) in [("rare", "ERROR"), ("common", r"in \d+ms"), ("none", "panic")] {
+        let regex = Regex::new(pattern).unwrap();
+        assert_eq!(
+            count_per_line(&regex, Cursor::new(&log)),
+            ch9_grepr::count_matching_lines(pattern, Cursor::new(&log)).unwrap()
+        );
+        group.bench_function(format!("per_line/{name}"), |b| {
+            b.iter(|| count_per_line(&regex, Cursor::new(black_box(&log))))
+        });
+        group.bench_function(format!("chunked/{name}"), |b| {
+            b.iter(|| ch9_grepr::count_matching_lines(pattern, Cursor::new(black_box(&log))).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, search);
+criterion_main!(benches);
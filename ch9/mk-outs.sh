@@ -46,4 +46,124 @@ grep -ci the $DIR/*.txt > "$OUT_DIR/all.the.lowercase.insensitive.count"
 grep -cri the tests/inputs > "$OUT_DIR/the.recursive.insensitive.count"
 
 # STDIN, insensitive, count
-cat tests/inputs/*.txt | grep -ci the - > "$OUT_DIR/the.recursive.insensitive.count.stdin"
\ No newline at end of file
+cat tests/inputs/*.txt | grep -ci the - > "$OUT_DIR/the.recursive.insensitive.count.stdin"
+# Context
+grep -A 1 The $DIR/bustle.txt > "$OUT_DIR/bustle.txt.the.capitalized.A1"
+grep -B 2 the $DIR/bustle.txt > "$OUT_DIR/bustle.txt.the.lowercase.B2"
+grep -C 1 -i nobody $DIR/nobody.txt > "$OUT_DIR/nobody.txt.insensitive.C1"
+grep -v -A 1 e $DIR/bustle.txt > "$OUT_DIR/bustle.txt.e.invert.A1"
+
+# Context, multiple files
+grep -C 1 -B 0 -i the $DIR/bustle.txt $DIR/fox.txt $DIR/nobody.txt > "$OUT_DIR/all.the.lowercase.insensitive.C1B0"
+
+# Line numbers, byte offsets and only-matching
+grep -n the $DIR/bustle.txt > "$OUT_DIR/bustle.txt.the.lowercase.n"
+grep -b the $DIR/bustle.txt > "$OUT_DIR/bustle.txt.the.lowercase.b"
+grep -obni the $DIR/bustle.txt > "$OUT_DIR/bustle.txt.the.lowercase.insensitive.onb"
+grep -n -C 1 Nobody $DIR/nobody.txt $DIR/fox.txt > "$OUT_DIR/all.nobody.n.C1"
+
+# Forcing and suppressing file names
+grep -H The $DIR/bustle.txt > "$OUT_DIR/bustle.txt.the.capitalized.H"
+grep -h The $DIR/*.txt > "$OUT_DIR/all.the.capitalized.h"
+
+# Colors
+GREP_COLORS= grep --color=always -n -C 1 -i the $DIR/bustle.txt $DIR/fox.txt > "$OUT_DIR/all.the.lowercase.insensitive.color"
+GREP_COLORS='mt=01;32:fn=34:sl=4:ne' grep --color=always -H Nobody $DIR/nobody.txt > "$OUT_DIR/nobody.txt.color.grep_colors"
+
+# File names only and maximum counts
+grep -l -i the $DIR/bustle.txt $DIR/empty.txt $DIR/fox.txt $DIR/nobody.txt > "$OUT_DIR/all.the.lowercase.insensitive.l"
+grep -L Nobody $DIR/bustle.txt $DIR/empty.txt $DIR/fox.txt $DIR/nobody.txt > "$OUT_DIR/all.nobody.L"
+grep -m 1 -A 2 -n the $DIR/bustle.txt > "$OUT_DIR/bustle.txt.the.lowercase.m1A2"
+grep -c -m 1 -i the $DIR/bustle.txt $DIR/fox.txt $DIR/nobody.txt > "$OUT_DIR/all.the.lowercase.insensitive.m1.count"
+
+# Multiple patterns and pattern syntaxes
+grep -e fox -e Nobody $DIR/fox.txt $DIR/nobody.txt > "$OUT_DIR/all.fox.nobody.e"
+grep -f tests/patterns.txt $DIR/bustle.txt $DIR/fox.txt $DIR/nobody.txt > "$OUT_DIR/all.patterns.f"
+grep -F -o -e . -e The $DIR/bustle.txt > "$OUT_DIR/bustle.txt.fixed.o"
+grep -w -i the $DIR/bustle.txt > "$OUT_DIR/bustle.txt.the.insensitive.w"
+grep -F -x 'Are you—Nobody—too?' $DIR/nobody.txt > "$OUT_DIR/nobody.txt.x"
+grep -G -o '\(No\|Some\)body' $DIR/nobody.txt > "$OUT_DIR/nobody.txt.basic.o"
+grep -P -o 'N\w+(?=!)|\w+(?=—too)' $DIR/nobody.txt > "$OUT_DIR/nobody.txt.perl.o"
+
+# Walking directories; grep does not sort files, grepr does
+TREE="tests/tree"
+grep -r --include='*.txt' --exclude-dir=build fox $TREE | sort -s -t: -k1,1 > "$OUT_DIR/tree.fox.include.exclude-dir"
+grep -r --exclude='*.txt' --exclude='.*' -l fox $TREE | sort > "$OUT_DIR/tree.fox.exclude.l"
+grep -a fox $TREE/data.bin > "$OUT_DIR/data.bin.fox.a"
+grep -c fox $TREE/data.bin > "$OUT_DIR/data.bin.fox.count"
+
+# grep has no .gitignore support and reports binary files differently
+cat > "$OUT_DIR/tree.fox.recursive" <<'END'
+tests/tree/app.log:fox in a log
+tests/tree/build/out.txt:fox was built
+Binary file tests/tree/data.bin matches
+tests/tree/notes.txt:The quick fox
+tests/tree/src/main.txt:a fox in the source
+END
+
+cat > "$OUT_DIR/tree.fox.gitignore" <<'END'
+Binary file tests/tree/data.bin matches
+tests/tree/notes.txt:The quick fox
+tests/tree/src/main.txt:a fox in the source
+END
+
+# Compressed files, decompressed by each tool for grep to read
+ZIP="tests/zip"
+{
+    grep -H ERROR $ZIP/app.log
+    gzip -dc $ZIP/app.log.1.gz | grep -H --label=$ZIP/app.log.1.gz ERROR
+    bzip2 -dc $ZIP/app.log.2.bz2 | grep -H --label=$ZIP/app.log.2.bz2 ERROR
+    xz -dc $ZIP/app.log.3.xz | grep -H --label=$ZIP/app.log.3.xz ERROR
+    zstd -dc $ZIP/app.log.4.zst | grep -H --label=$ZIP/app.log.4.zst ERROR
+} > "$OUT_DIR/zip.error.recursive"
+{
+    gzip -dc $ZIP/app.log.1.gz | grep -c -H --label=$ZIP/app.log.1.gz INFO
+    bzip2 -dc $ZIP/app.log.2.bz2 | grep -c -H --label=$ZIP/app.log.2.bz2 INFO
+    xz -dc $ZIP/app.log.3.xz | grep -c -H --label=$ZIP/app.log.3.xz INFO
+    zstd -dc $ZIP/app.log.4.zst | grep -c -H --label=$ZIP/app.log.4.zst INFO
+} > "$OUT_DIR/zip.info.count"
+
+# grep has no JSON output; elapsed times are left out, as the tests ignore them
+cat > "$OUT_DIR/all.the.insensitive.B1.json" <<'END'
+{"type":"begin","data":{"path":{"text":"tests/inputs/bustle.txt"}}}
+{"type":"match","data":{"path":{"text":"tests/inputs/bustle.txt"},"lines":{"text":"The bustle in a house\n"},"line_number":1,"absolute_offset":0,"submatches":[{"match":{"text":"The"},"start":0,"end":3}]}}
+{"type":"match","data":{"path":{"text":"tests/inputs/bustle.txt"},"lines":{"text":"The morning after death\n"},"line_number":2,"absolute_offset":22,"submatches":[{"match":{"text":"The"},"start":0,"end":3}]}}
+{"type":"context","data":{"path":{"text":"tests/inputs/bustle.txt"},"lines":{"text":"\n"},"line_number":5,"absolute_offset":96,"submatches":[]}}
+{"type":"match","data":{"path":{"text":"tests/inputs/bustle.txt"},"lines":{"text":"The sweeping up the heart,\n"},"line_number":6,"absolute_offset":97,"submatches":[{"match":{"text":"The"},"start":0,"end":3},{"match":{"text":"the"},"start":16,"end":19}]}}
+{"type":"end","data":{"path":{"text":"tests/inputs/bustle.txt"},"binary_offset":null,"stats":{"searches":1,"searches_with_match":1,"bytes_searched":192,"bytes_printed":882,"matched_lines":3,"matches":4}}}
+{"type":"begin","data":{"path":{"text":"tests/inputs/fox.txt"}}}
+{"type":"match","data":{"path":{"text":"tests/inputs/fox.txt"},"lines":{"text":"The quick brown fox jumps over the lazy dog."},"line_number":1,"absolute_offset":0,"submatches":[{"match":{"text":"The"},"start":0,"end":3},{"match":{"text":"the"},"start":31,"end":34}]}}
+{"type":"end","data":{"path":{"text":"tests/inputs/fox.txt"},"binary_offset":null,"stats":{"searches":1,"searches_with_match":1,"bytes_searched":44,"bytes_printed":333,"matched_lines":1,"matches":2}}}
+{"type":"summary","data":{"stats":{"searches":3,"searches_with_match":2,"bytes_searched":236,"bytes_printed":1215,"matched_lines":4,"matches":6}}}
+END
+
+cat > "$OUT_DIR/data.bin.fox.json" <<'END'
+{"type":"begin","data":{"path":{"text":"tests/tree/data.bin"}}}
+{"type":"end","data":{"path":{"text":"tests/tree/data.bin"},"binary_offset":6,"stats":{"searches":1,"searches_with_match":1,"bytes_searched":16,"bytes_printed":64,"matched_lines":1,"matches":0}}}
+{"type":"summary","data":{"stats":{"searches":1,"searches_with_match":1,"bytes_searched":16,"bytes_printed":64,"matched_lines":1,"matches":0}}}
+END
+
+# Replacements, made by sed on what grep selects
+grep -E '(fox|dog)' $DIR/fox.txt | sed -E 's/(fox|dog)/\1-\1/g' > "$OUT_DIR/fox.txt.replace"
+grep -n -i the $DIR/bustle.txt | sed 's/[Tt][Hh][Ee]/<&>/g' > "$OUT_DIR/bustle.txt.the.insensitive.n.replace"
+grep -o -b -P '\w+(?=ody)' $DIR/nobody.txt | sed -E 's/^([0-9]+:)(.*)$/\1[\2]/' > "$OUT_DIR/nobody.txt.perl.o.replace"
+sed -E 's/(\w+)est/\1er/g' $DIR/bustle.txt > "$OUT_DIR/bustle.txt.in-place"
+
+# The diff --dry-run prints, which patch applies to give the same files
+cat > "$OUT_DIR/all.in-place.dry-run" <<'END'
+--- tests/inputs/bustle.txt
++++ tests/inputs/bustle.txt
+@@ -3 +3 @@
+-Is solemnest of industries
++Is solemner of industries
+@@ -6 +6 @@
+-The sweeping up the heart,
++The sweeping up er heart,
+--- tests/inputs/fox.txt
++++ tests/inputs/fox.txt
+@@ -1 +1 @@
+-The quick brown fox jumps over the lazy dog.
+\ No newline at end of file
++The quick brown fox jumps over er lazy dog.
+\ No newline at end of file
+END
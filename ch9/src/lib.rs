@@ -1,22 +1,125 @@
-use clap::{Command, Arg, ArgAction};
-use regex::{Regex, RegexBuilder};
+use aho_corasick::{AhoCorasick, MatchKind};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use bzip2::read::MultiBzDecoder;
+use clap::{parser::ValueSource, ArgMatches, Command, Arg, ArgAction};
+use flate2::read::MultiGzDecoder;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use memchr::{memchr, memchr_iter, memrchr};
+use regex::bytes::{Regex, RegexBuilder};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::env;
 use std::error::Error;
 use std::fs::{self, File};
-use std::io::{self, BufRead, BufReader};
-use std::mem;
-use walkdir::WalkDir;
+use std::io::{self, BufReader, IsTerminal, Read, Write};
+use std::iter::Peekable;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::Path;
+use std::process;
+use std::str::Chars;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{mem, str};
+use xz2::read::XzDecoder;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+// Files are read this much at a time, or more to hold a longer line
+const CHUNK_SIZE: usize = 256 * 1024;
+// A NUL byte this close to the start of a file makes it binary
+const BLOCK_SIZE: usize = 8 * 1024;
+
 #[derive(Debug)]
 pub struct Config {
-    pattern: Regex,
+    matcher: Matcher,
     files: Vec<String>,
-    recursive: bool,
+    walk: Walk,
     count: bool,
     invert_match: bool,
+    before_context: usize,
+    after_context: usize,
+    line_number: bool,
+    byte_offset: bool,
+    only_matching: bool,
+    with_filename: Option<bool>,
+    colors: Option<Colors>,
+    files_with_matches: bool,
+    files_without_match: bool,
+    quiet: bool,
+    max_count: Option<usize>,
+    text: bool,
+    search_zip: bool,
+    json: bool,
+    replace: Option<String>,
+    in_place: bool,
+    dry_run: bool,
+}
+
+// Which files are searched under the directories given
+#[derive(Debug)]
+struct Walk {
+    recursive: bool,
+    gitignore: bool,
+    include: GlobSet,
+    exclude: GlobSet,
+    exclude_dir: GlobSet,
+}
+
+// SGR sequences for each part of the output, named as in GNU grep's
+// GREP_COLORS; an empty sequence leaves that part uncolored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Colors {
+    selected_match: String,
+    context_match: String,
+    selected_line: String,
+    context_line: String,
+    filename: String,
+    line_number: String,
+    byte_offset: String,
+    separator: String,
+    reverse: bool,
+    erase_line: bool,
+}
+
+// A line read by `find_lines`, with its 1-based line number and the
+// byte offset of its start in the input. The text is without the newline,
+// which only the last line of the input can lack.
+#[derive(Debug, PartialEq)]
+struct Line<'a> {
+    number: usize,
+    offset: usize,
+    text: &'a [u8],
+    newline: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum Event<'a> {
+    Match(Line<'a>),
+    Context(Line<'a>),
+    // Starts a group of lines not adjacent to the previous one
+    Break,
+}
+
+// How patterns are interpreted, as selected with -E, -F, -G and -P
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Syntax {
+    Extended,
+    Fixed,
+    Basic,
+    Perl,
 }
 
+// The compiled patterns; a line is selected when any of them matches
+#[derive(Debug)]
+enum Matcher {
+    Regex(Regex),
+    Literals(AhoCorasick),
+    Perl(fancy_regex::Regex),
+}
 
 pub fn get_args() -> MyResult<Config> {
     let matches = Command::new("ch9-grepr")
@@ -27,7 +130,7 @@ pub fn get_args() -> MyResult<Config> {
             Arg::new("pattern")
                 .value_name("PATTERN")
                 .help("Search pattern")
-                .required(true)
+                .required_unless_present_any(["regexp", "pattern_file"])
                 .num_args(1)
         )
         .arg(
@@ -44,6 +147,69 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Case-insensitive")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("regexp")
+                .value_name("PATTERN")
+                .short('e')
+                .long("regexp")
+                .help("Search pattern, may be repeated")
+                .allow_hyphen_values(true)
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("pattern_file")
+                .value_name("FILE")
+                .short('f')
+                .long("file")
+                .help("Read search patterns from FILE, one per line")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("extended_regexp")
+                .short('E')
+                .long("extended-regexp")
+                .help("Patterns are extended regular expressions (default)")
+                .action(ArgAction::SetTrue)
+                .overrides_with_all(["fixed_strings", "basic_regexp", "perl_regexp"]),
+        )
+        .arg(
+            Arg::new("fixed_strings")
+                .short('F')
+                .long("fixed-strings")
+                .help("Patterns are literal strings")
+                .action(ArgAction::SetTrue)
+                .overrides_with_all(["extended_regexp", "basic_regexp", "perl_regexp"]),
+        )
+        .arg(
+            Arg::new("basic_regexp")
+                .short('G')
+                .long("basic-regexp")
+                .help("Patterns are basic regular expressions")
+                .action(ArgAction::SetTrue)
+                .overrides_with_all(["extended_regexp", "fixed_strings", "perl_regexp"]),
+        )
+        .arg(
+            Arg::new("perl_regexp")
+                .short('P')
+                .long("perl-regexp")
+                .help("Patterns are Perl regular expressions")
+                .action(ArgAction::SetTrue)
+                .overrides_with_all(["extended_regexp", "fixed_strings", "basic_regexp"]),
+        )
+        .arg(
+            Arg::new("word_regexp")
+                .short('w')
+                .long("word-regexp")
+                .help("Match only whole words")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("line_regexp")
+                .short('x')
+                .long("line-regexp")
+                .help("Match only whole lines")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("recursive")
                 .short('r')
@@ -51,6 +217,47 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Selected field")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("include")
+                .value_name("GLOB")
+                .long("include")
+                .help("Search only files whose base name matches GLOB")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("exclude")
+                .value_name("GLOB")
+                .long("exclude")
+                .help("Skip files whose base name matches GLOB")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("exclude_dir")
+                .value_name("GLOB")
+                .long("exclude-dir")
+                .help("Skip directories whose base name matches GLOB when recursing")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("gitignore")
+                .long("gitignore")
+                .help("Skip files ignored by .gitignore files when recursing")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("text")
+                .short('a')
+                .long("text")
+                .help("Search binary files as if they were text")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("search_zip")
+                .short('z')
+                .long("search-zip")
+                .help("Search inside gzip, bzip2, xz and zstd compressed files")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("count")
                 .short('c')
@@ -65,168 +272,1864 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Invert-match")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("after_context")
+                .value_name("NUM")
+                .short('A')
+                .long("after-context")
+                .help("Print NUM lines of trailing context")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("before_context")
+                .value_name("NUM")
+                .short('B')
+                .long("before-context")
+                .help("Print NUM lines of leading context")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("context")
+                .value_name("NUM")
+                .short('C')
+                .long("context")
+                .help("Print NUM lines of leading and trailing context")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("line_number")
+                .short('n')
+                .long("line-number")
+                .help("Prefix each line with its line number")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("byte_offset")
+                .short('b')
+                .long("byte-offset")
+                .help("Prefix each line with its byte offset")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("only_matching")
+                .short('o')
+                .long("only-matching")
+                .help("Print only the matched parts of a line, one per line")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("with_filename")
+                .short('H')
+                .long("with-filename")
+                .help("Print the file name for each match")
+                .action(ArgAction::SetTrue)
+                .overrides_with("no_filename"),
+        )
+        .arg(
+            Arg::new("no_filename")
+                .short('h')
+                .long("no-filename")
+                .help("Never print file names")
+                .action(ArgAction::SetTrue)
+                .overrides_with("with_filename"),
+        )
+        .arg(
+            Arg::new("color")
+                .value_name("WHEN")
+                .long("color")
+                .visible_alias("colour")
+                .help("Highlight matches, configured by GREP_COLORS")
+                .value_parser(["auto", "always", "never"])
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("auto")
+                .default_value("never"),
+        )
+        .arg(
+            Arg::new("files_with_matches")
+                .short('l')
+                .long("files-with-matches")
+                .help("Print only the names of files with a match")
+                .action(ArgAction::SetTrue)
+                .overrides_with("files_without_match"),
+        )
+        .arg(
+            Arg::new("files_without_match")
+                .short('L')
+                .long("files-without-match")
+                .help("Print only the names of files without a match")
+                .action(ArgAction::SetTrue)
+                .overrides_with("files_with_matches"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .visible_alias("silent")
+                .help("Print nothing, exit with zero status on the first match")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print results as JSON Lines")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["count", "files_with_matches", "files_without_match", "quiet"]),
+        )
+        .arg(
+            Arg::new("replace")
+                .value_name("REPLACEMENT")
+                .long("replace")
+                .help("Print matches replaced by REPLACEMENT, which may refer to groups as $1 or ${name}")
+                .conflicts_with("json"),
+        )
+        .arg(
+            Arg::new("in_place")
+                .long("in-place")
+                .help("Rewrite files with the replacements made")
+                .action(ArgAction::SetTrue)
+                .requires("replace")
+                .conflicts_with_all([
+                    "invert",
+                    "count",
+                    "files_with_matches",
+                    "files_without_match",
+                    "quiet",
+                    "search_zip",
+                ]),
+        )
+        .arg(
+            Arg::new("dry_run")
+                .long("dry-run")
+                .help("Print the changes --in-place would make as a diff")
+                .action(ArgAction::SetTrue)
+                .requires("in_place"),
+        )
+        .arg(
+            Arg::new("max_count")
+                .value_name("NUM")
+                .short('m')
+                .long("max-count")
+                .help("Stop reading a file after NUM selected lines")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .disable_help_flag(true)
+        .arg(
+            Arg::new("help")
+                .long("help")
+                .help("Print help")
+                .action(ArgAction::Help),
+        )
         .get_matches();
 
-    let pattern= matches.get_one::<String>("pattern").unwrap();
-    let pattern = RegexBuilder::new(pattern)
-        .case_insensitive(matches.get_flag("insensitive"))
-        .build()
-        .map_err(|_| format!("Invalid pattern \"{}\"", pattern))?;
-
-    let files = matches.get_many::<String>("files")
+    let mut files = matches.get_many::<String>("files")
         .unwrap()
         .cloned()
         .collect::<Vec<String>>();
 
-    let recursive = matches.get_flag("recursive");
+    // A newline separates patterns wherever they come from
+    let mut patterns = vec![];
+    for pattern in matches.get_many::<String>("regexp").unwrap_or_default() {
+        patterns.extend(pattern.split('\n').map(String::from));
+    }
+    for filename in matches.get_many::<String>("pattern_file").unwrap_or_default() {
+        patterns.extend(read_patterns(filename)?);
+    }
+    let pattern = matches.get_one::<String>("pattern").cloned();
+    if matches.contains_id("regexp") || matches.contains_id("pattern_file") {
+        // With patterns given as options, the first operand is a file
+        if let Some(filename) = pattern {
+            if matches.value_source("files") == Some(ValueSource::DefaultValue) {
+                files.clear();
+            }
+            files.insert(0, filename);
+        }
+    } else if let Some(pattern) = pattern {
+        patterns.extend(pattern.split('\n').map(String::from));
+    }
+
+    let syntax = if matches.get_flag("fixed_strings") {
+        Syntax::Fixed
+    } else if matches.get_flag("basic_regexp") {
+        Syntax::Basic
+    } else if matches.get_flag("perl_regexp") {
+        Syntax::Perl
+    } else {
+        Syntax::Extended
+    };
+    let matcher = Matcher::new(
+        &patterns,
+        syntax,
+        matches.get_flag("insensitive"),
+        matches.get_flag("word_regexp"),
+        matches.get_flag("line_regexp"),
+    )?;
+
+    let walk = Walk {
+        recursive: matches.get_flag("recursive"),
+        gitignore: matches.get_flag("gitignore"),
+        include: build_globs(&matches, "include")?,
+        exclude: build_globs(&matches, "exclude")?,
+        exclude_dir: build_globs(&matches, "exclude_dir")?,
+    };
     let count = matches.get_flag("count");
     let invert_match= matches.get_flag("invert");
 
+    // -A and -B take precedence over -C
+    let context = matches.get_one::<usize>("context").copied().unwrap_or(0);
+    let before_context = matches.get_one::<usize>("before_context").copied().unwrap_or(context);
+    let after_context = matches.get_one::<usize>("after_context").copied().unwrap_or(context);
+
+    let with_filename = if matches.get_flag("with_filename") {
+        Some(true)
+    } else if matches.get_flag("no_filename") {
+        Some(false)
+    } else {
+        None
+    };
+
+    let color = match matches.get_one::<String>("color").map(String::as_str) {
+        Some("always") => true,
+        Some("auto") => io::stdout().is_terminal() && env::var("TERM").is_ok_and(|term| term != "dumb"),
+        _ => false,
+    };
+    let colors = color.then(|| Colors::parse(&env::var("GREP_COLORS").unwrap_or_default()));
+
     Ok(Config{
-        pattern,
+        matcher,
         files,
-        recursive,
+        walk,
         count,
         invert_match,
+        before_context,
+        after_context,
+        line_number: matches.get_flag("line_number"),
+        byte_offset: matches.get_flag("byte_offset"),
+        only_matching: matches.get_flag("only_matching"),
+        with_filename,
+        colors,
+        files_with_matches: matches.get_flag("files_with_matches"),
+        files_without_match: matches.get_flag("files_without_match"),
+        quiet: matches.get_flag("quiet"),
+        max_count: matches.get_one::<usize>("max_count").copied(),
+        text: matches.get_flag("text"),
+        search_zip: matches.get_flag("search_zip"),
+        json: matches.get_flag("json"),
+        replace: matches.get_one::<String>("replace").cloned(),
+        in_place: matches.get_flag("in_place"),
+        dry_run: matches.get_flag("dry_run"),
     })
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let entries = find_files(&config.files, config.recursive);
-    let num_files = entries.len();
-    let print = |fname: &str, val: &str| {
-        if num_files > 1 {
-            print!("{fname}:{val}");
+fn build_globs(matches: &ArgMatches, id: &str) -> MyResult<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in matches.get_many::<String>(id).unwrap_or_default() {
+        builder.add(Glob::new(glob)?);
+    }
+    Ok(builder.build()?)
+}
+
+impl Matcher {
+    fn new(
+        patterns: &[String],
+        syntax: Syntax,
+        insensitive: bool,
+        word: bool,
+        line: bool,
+    ) -> MyResult<Matcher> {
+        // Aho-Corasick only folds ASCII case and knows nothing of words
+        // or lines, the regex engines handle everything else
+        let literals = syntax == Syntax::Fixed
+            && !word
+            && !line
+            && (!insensitive || patterns.iter().all(|p| p.is_ascii()));
+        if literals || patterns.is_empty() {
+            let literals = AhoCorasick::builder()
+                .match_kind(MatchKind::LeftmostLongest)
+                .ascii_case_insensitive(insensitive)
+                .build(patterns)?;
+            return Ok(Matcher::Literals(literals));
+        }
+
+        let mut sources = vec![];
+        for pattern in patterns {
+            let source = match syntax {
+                Syntax::Fixed => regex::escape(pattern),
+                Syntax::Basic => bre_to_ere(pattern),
+                Syntax::Extended | Syntax::Perl => pattern.clone(),
+            };
+            // Compile each pattern alone to report which one is invalid
+            let valid = if syntax == Syntax::Perl {
+                fancy_regex::Regex::new(&source).is_ok()
+            } else {
+                Regex::new(&source).is_ok()
+            };
+            if !valid {
+                return Err(format!("Invalid pattern \"{}\"", pattern).into());
+            }
+            sources.push(format!("(?:{})", source));
+        }
+
+        let (open, close) = match (line, word, syntax) {
+            (true, _, _) => ("^(?:", ")$"),
+            (false, true, Syntax::Perl) => (r"(?<!\w)(?:", r")(?!\w)"),
+            (false, true, _) => (r"\b{start-half}(?:", r")\b{end-half}"),
+            (false, false, _) => ("(?:", ")"),
+        };
+        let source = format!("{}{}{}", open, sources.join("|"), close);
+        Ok(if syntax == Syntax::Perl {
+            let regex = fancy_regex::RegexBuilder::new(&source)
+                .case_insensitive(insensitive)
+                .build()?;
+            Matcher::Perl(regex)
+        } else {
+            // Searching whole chunks, ^ and $ must match around newlines
+            let regex = RegexBuilder::new(&source)
+                .case_insensitive(insensitive)
+                .multi_line(true)
+                .build()?;
+            Matcher::Regex(regex)
+        })
+    }
+
+    // Perl patterns fail when matching takes too much backtracking
+    fn is_match(&self, text: &[u8]) -> MyResult<bool> {
+        Ok(match self {
+            Matcher::Regex(regex) => regex.is_match(text),
+            Matcher::Literals(literals) => literals.is_match(text),
+            Matcher::Perl(regex) => regex.is_match(&String::from_utf8_lossy(text))?,
+        })
+    }
+
+    // The non-empty matches in `text`; Perl patterns only find them in
+    // valid UTF-8
+    fn find_iter(&self, text: &[u8]) -> MyResult<Vec<Range<usize>>> {
+        let found: Vec<Range<usize>> = match self {
+            Matcher::Regex(regex) => regex.find_iter(text).map(|m| m.range()).collect(),
+            Matcher::Literals(literals) => literals.find_iter(text).map(|m| m.range()).collect(),
+            Matcher::Perl(regex) => match str::from_utf8(text) {
+                Ok(text) => {
+                    let mut found = vec![];
+                    for m in regex.find_iter(text) {
+                        found.push(m?.range());
+                    }
+                    found
+                }
+                Err(_) => vec![],
+            },
+        };
+        Ok(found.into_iter().filter(|m| !m.is_empty()).collect())
+    }
+
+    // The non-empty matches in `text`, each with `replacement` expanded for
+    // it. Literals only have the whole match, group 0.
+    fn replacements(&self, text: &[u8], replacement: &str) -> MyResult<Vec<(Range<usize>, Vec<u8>)>> {
+        let found: Vec<(Range<usize>, Vec<u8>)> = match self {
+            Matcher::Regex(regex) => regex.captures_iter(text)
+                .map(|caps| {
+                    let mut expanded = vec![];
+                    caps.expand(replacement.as_bytes(), &mut expanded);
+                    (caps.get_match().range(), expanded)
+                })
+                .collect(),
+            Matcher::Literals(literals) => literals.find_iter(text)
+                .map(|m| (m.range(), expand_literal(replacement, &text[m.range()])))
+                .collect(),
+            Matcher::Perl(regex) => match str::from_utf8(text) {
+                Ok(text) => {
+                    let mut found = vec![];
+                    for caps in regex.captures_iter(text) {
+                        let caps = caps?;
+                        let mut expanded = String::new();
+                        caps.expand(replacement, &mut expanded);
+                        found.push((caps.get(0).unwrap().range(), expanded.into_bytes()));
+                    }
+                    found
+                }
+                Err(_) => vec![],
+            },
+        };
+        Ok(found.into_iter().filter(|(m, _)| !m.is_empty()).collect())
+    }
+
+    fn replace(&self, text: &[u8], replacement: &str) -> MyResult<Vec<u8>> {
+        let mut out = vec![];
+        let mut last = 0;
+        for (found, expanded) in self.replacements(text, replacement)? {
+            out.extend(&text[last..found.start]);
+            out.extend(expanded);
+            last = found.end;
+        }
+        out.extend(&text[last..]);
+        Ok(out)
+    }
+
+    // Finds the first line from `start` on that has a match, searching the
+    // rest of `buf` at once rather than line by line
+    fn find_line(&self, buf: &[u8], mut start: usize) -> MyResult<Option<Range<usize>>> {
+        while start < buf.len() {
+            let found = match self {
+                Matcher::Regex(regex) => regex.find_at(buf, start).map(|m| m.range()),
+                Matcher::Literals(literals) => {
+                    let input = aho_corasick::Input::new(buf).range(start..);
+                    literals.find(input).map(|m| m.range())
+                }
+                // Lookaround needs a line at a time
+                Matcher::Perl(_) => Some(start..start),
+            };
+            let Some(found) = found else {
+                return Ok(None);
+            };
+            let line_start = memrchr(b'\n', &buf[start..found.start]).map_or(start, |i| start + i + 1);
+            let line_end = memchr(b'\n', &buf[found.start..]).map_or(buf.len(), |i| found.start + i + 1);
+
+            // A match across a newline is only a candidate for its line
+            let text = &buf[line_start..line_end];
+            let text = text.strip_suffix(b"\n").unwrap_or(text);
+            if found.end <= line_start + text.len() && !matches!(self, Matcher::Perl(_))
+                || self.is_match(text)?
+            {
+                return Ok(Some(line_start..line_end));
+            }
+            start = line_end;
+        }
+        Ok(None)
+    }
+}
+
+// Expands `replacement` the way the regex crate does for a match with no
+// groups but the whole one: $0 or ${0} is the match, $$ a dollar sign and
+// any other group is empty
+fn expand_literal(replacement: &str, found: &[u8]) -> Vec<u8> {
+    let is_name = |c: char| c == '_' || c.is_ascii_alphanumeric();
+    let mut out = vec![];
+    let mut rest = replacement;
+    while let Some(i) = rest.find('$') {
+        out.extend(&rest.as_bytes()[..i]);
+        rest = &rest[i + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push(b'$');
+            rest = after;
+            continue;
+        }
+        let (name, after) = match rest.strip_prefix('{').and_then(|braced| braced.split_once('}')) {
+            Some(split) => split,
+            None => rest.split_at(rest.find(|c| !is_name(c)).unwrap_or(rest.len())),
+        };
+        // A lone $ is itself, but ${} names a group like any other
+        if name.is_empty() && after.len() == rest.len() {
+            out.push(b'$');
+            continue;
+        }
+        if name == "0" {
+            out.extend(found);
+        }
+        rest = after;
+    }
+    out.extend(rest.as_bytes());
+    out
+}
+
+// Reads one pattern per line from `filename`, or from STDIN for "-"
+fn read_patterns(filename: &str) -> MyResult<Vec<String>> {
+    let mut text = vec![];
+    open(filename, false)
+        .and_then(|mut file| Ok(file.read_to_end(&mut text)?))
+        .map_err(|e| format!("{}: {}", filename, e))?;
+    Ok(String::from_utf8_lossy(&text).lines().map(String::from).collect())
+}
+
+// Translates a POSIX basic regular expression to the extended syntax:
+// \( \) \{ \} \| \+ \? are operators while their unescaped forms and a
+// leading `*` are literals.
+fn bre_to_ere(pattern: &str) -> String {
+    let mut out = String::new();
+    let mut chars = pattern.chars().peekable();
+    // Whether a `*` here is literal
+    let mut at_start = true;
+    while let Some(c) = chars.next() {
+        let star_literal = at_start;
+        at_start = false;
+        match c {
+            '\\' => match chars.next() {
+                Some(c @ ('(' | '|')) => {
+                    out.push(c);
+                    at_start = true;
+                }
+                Some(c @ (')' | '{' | '}' | '+' | '?')) => out.push(c),
+                Some(c) => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                // Left for the regex engine to reject
+                None => out.push('\\'),
+            },
+            '(' | ')' | '{' | '}' | '|' | '+' | '?' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '*' if star_literal => out.push_str("\\*"),
+            '^' if star_literal => {
+                out.push(c);
+                at_start = true;
+            }
+            '[' => push_bracket(&mut chars, &mut out),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// Copies a bracket expression after its `[`, escaping the characters that
+// are literal in POSIX brackets but special to the regex crate
+fn push_bracket(chars: &mut Peekable<Chars>, out: &mut String) {
+    out.push('[');
+    if chars.next_if_eq(&'^').is_some() {
+        out.push('^');
+    }
+    if chars.next_if_eq(&']').is_some() {
+        out.push_str("\\]");
+    }
+    while let Some(c) = chars.next() {
+        match c {
+            ']' => {
+                out.push(c);
+                return;
+            }
+            // A character class such as [:alpha:]
+            '[' if chars.peek() == Some(&':') => {
+                out.push(c);
+                for c in chars.by_ref() {
+                    out.push(c);
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            '[' | '\\' | '&' | '~' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+}
+
+impl Colors {
+    // Applies a GREP_COLORS spec such as "ms=01;31:fn=35:ne" on top of
+    // GNU grep's defaults, ignoring capabilities it does not know.
+    fn parse(spec: &str) -> Colors {
+        let mut colors = Colors {
+            selected_match: "01;31".to_string(),
+            context_match: "01;31".to_string(),
+            selected_line: String::new(),
+            context_line: String::new(),
+            filename: "35".to_string(),
+            line_number: "32".to_string(),
+            byte_offset: "32".to_string(),
+            separator: "36".to_string(),
+            reverse: false,
+            erase_line: true,
+        };
+        for cap in spec.split(':') {
+            let (name, value) = cap.split_once('=').unwrap_or((cap, ""));
+            let value = value.to_string();
+            match name {
+                "mt" => {
+                    colors.selected_match = value.clone();
+                    colors.context_match = value;
+                }
+                "ms" => colors.selected_match = value,
+                "mc" => colors.context_match = value,
+                "sl" => colors.selected_line = value,
+                "cx" => colors.context_line = value,
+                "fn" => colors.filename = value,
+                "ln" => colors.line_number = value,
+                "bn" => colors.byte_offset = value,
+                "se" => colors.separator = value,
+                "rv" => colors.reverse = true,
+                "ne" => colors.erase_line = false,
+                _ => {}
+            }
+        }
+        colors
+    }
+
+    fn start(&self, sgr: &str) -> String {
+        match (sgr, self.erase_line) {
+            ("", _) => String::new(),
+            (_, true) => format!("\x1b[{sgr}m\x1b[K"),
+            (_, false) => format!("\x1b[{sgr}m"),
+        }
+    }
+
+    fn paint(&self, sgr: &str, text: &[u8]) -> Vec<u8> {
+        if sgr.is_empty() || text.is_empty() {
+            return text.to_vec();
+        }
+        let end = if self.erase_line { "\x1b[m\x1b[K" } else { "\x1b[m" };
+        [self.start(sgr).as_bytes(), text, end.as_bytes()].concat()
+    }
+}
+
+struct Printer<'a> {
+    config: &'a Config,
+    show_filename: bool,
+    output: &'a Output,
+    // Where the file is in the order of output
+    index: usize,
+    // What is printed while files before this one are still searched
+    out: Vec<u8>,
+    printed: usize,
+    // Whether the output starts a new group of context lines
+    separated: bool,
+    // Whether the JSON begin event or diff header for the file was printed
+    begun: bool,
+    stats: Stats,
+}
+
+impl<'a> Printer<'a> {
+    fn new(config: &'a Config, show_filename: bool, output: &'a Output, index: usize) -> Printer<'a> {
+        Printer {
+            config,
+            show_filename,
+            output,
+            index,
+            out: vec![],
+            printed: 0,
+            separated: false,
+            begun: false,
+            stats: Stats::default(),
+        }
+    }
+
+    fn paint(&self, sgr: impl Fn(&Colors) -> &str, text: &[u8]) -> Vec<u8> {
+        match &self.config.colors {
+            Some(colors) => colors.paint(sgr(colors), text),
+            None => text.to_vec(),
+        }
+    }
+
+    fn prefix(&self, filename: &str, sep: &str, number: usize, offset: usize) -> Vec<u8> {
+        let sep = self.paint(|c| &c.separator, sep.as_bytes());
+        let mut prefix = vec![];
+        if self.show_filename {
+            prefix.extend(self.paint(|c| &c.filename, filename.as_bytes()));
+            prefix.extend(&sep);
+        }
+        if self.config.line_number {
+            prefix.extend(self.paint(|c| &c.line_number, number.to_string().as_bytes()));
+            prefix.extend(&sep);
+        }
+        if self.config.byte_offset {
+            prefix.extend(self.paint(|c| &c.byte_offset, offset.to_string().as_bytes()));
+            prefix.extend(&sep);
+        }
+        prefix
+    }
+
+    // Colors the matches in a line, and the text around them, emitting the
+    // same sequences as GNU grep
+    fn highlight(&self, text: &[u8], selected: bool) -> MyResult<Vec<u8>> {
+        let Some(colors) = &self.config.colors else {
+            return Ok(text.to_vec());
+        };
+        let (match_sgr, mut line_sgr) = if selected {
+            (&colors.selected_match, &colors.selected_line)
         } else {
-            print!("{val}");
+            (&colors.context_match, &colors.context_line)
+        };
+        if colors.reverse && self.config.invert_match {
+            line_sgr = if selected { &colors.context_line } else { &colors.selected_line };
+        }
+
+        let mut out = vec![];
+        let mut last = 0;
+        if !match_sgr.is_empty() {
+            for found in self.config.matcher.find_iter(text)? {
+                out.extend(colors.start(line_sgr).as_bytes());
+                out.extend(&text[last..found.start]);
+                out.extend(colors.paint(match_sgr, &text[found.clone()]));
+                last = found.end;
+            }
+        }
+        out.extend(colors.paint(line_sgr, &text[last..]));
+        Ok(out)
+    }
+
+    // A selected line with its matches replaced, and colored as matches
+    fn replaced(&self, text: &[u8], replacement: &str) -> MyResult<Vec<u8>> {
+        let mut out = vec![];
+        let mut last = 0;
+        for (found, expanded) in self.config.matcher.replacements(text, replacement)? {
+            out.extend(self.paint(|c| &c.selected_line, &text[last..found.start]));
+            out.extend(self.paint(|c| &c.selected_match, &expanded));
+            last = found.end;
         }
+        out.extend(self.paint(|c| &c.selected_line, &text[last..]));
+        Ok(out)
+    }
+
+    // Lines go straight to standard output once every file before this
+    // one is printed, and are kept until then
+    fn print_line(&mut self, line: &[u8]) -> io::Result<()> {
+        self.out.extend(line);
+        self.out.push(b'\n');
+        self.printed += line.len() + 1;
+        if self.output.is_head(self.index) {
+            let mut state = self.output.state.lock().unwrap();
+            self.output.print(&mut state, &mut self.separated, &self.out)?;
+            self.out.clear();
+        }
+        Ok(())
+    }
+
+    fn print_event(&mut self, filename: &str, event: Event) -> MyResult<()> {
+        if self.config.json {
+            return self.print_json_event(filename, event);
+        }
+        match event {
+            // The separator before the first group is up to what was
+            // printed for earlier files
+            Event::Break if self.printed == 0 => self.separated = true,
+            Event::Break => {
+                let separator = self.paint(|c| &c.separator, b"--");
+                self.print_line(&separator)?;
+            }
+            Event::Match(line) if self.config.only_matching && self.config.replace.is_some() => {
+                let replacement = self.config.replace.as_deref().unwrap_or_default();
+                for (found, expanded) in self.config.matcher.replacements(line.text, replacement)? {
+                    let mut out = self.prefix(filename, ":", line.number, line.offset + found.start);
+                    out.extend(self.paint(|c| &c.selected_match, &expanded));
+                    self.print_line(&out)?;
+                }
+            }
+            Event::Match(line) if self.config.only_matching => {
+                for found in self.config.matcher.find_iter(line.text)? {
+                    let mut out = self.prefix(filename, ":", line.number, line.offset + found.start);
+                    out.extend(self.paint(|c| &c.selected_match, &line.text[found]));
+                    self.print_line(&out)?;
+                }
+            }
+            Event::Match(line) => {
+                let mut out = self.prefix(filename, ":", line.number, line.offset);
+                match &self.config.replace {
+                    Some(replacement) => out.extend(self.replaced(line.text, replacement)?),
+                    None => out.extend(self.highlight(line.text, true)?),
+                }
+                self.print_line(&out)?;
+            }
+            Event::Context(line) => {
+                let mut out = self.prefix(filename, "-", line.number, line.offset);
+                out.extend(self.highlight(line.text, false)?);
+                self.print_line(&out)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_filename(&mut self, filename: &str) -> io::Result<()> {
+        let line = self.paint(|c| &c.filename, filename.as_bytes());
+        self.print_line(&line)
+    }
+
+    fn print_count(&mut self, filename: &str, count: usize) -> io::Result<()> {
+        let mut line = vec![];
+        if self.show_filename {
+            line.extend(self.paint(|c| &c.filename, filename.as_bytes()));
+            line.extend(self.paint(|c| &c.separator, b":"));
+        }
+        line.extend(count.to_string().as_bytes());
+        self.print_line(&line)
+    }
+
+    // Prints a changed line as a unified diff hunk, `new` being one line
+    // or more; without a newline `old` is the file's last line
+    fn print_diff(&mut self, filename: &str, (number, new_number): (usize, usize), old: &[u8], new: &[u8], newline: bool) -> io::Result<()> {
+        if !self.begun {
+            self.begun = true;
+            self.print_line(format!("--- {}", filename).as_bytes())?;
+            self.print_line(format!("+++ {}", filename).as_bytes())?;
+        }
+        let new_lines: Vec<&[u8]> = new.split(|&b| b == b'\n').collect();
+        let new_count = match new_lines.len() {
+            1 => String::new(),
+            count => format!(",{}", count),
+        };
+        self.print_line(format!("@@ -{} +{}{} @@", number, new_number, new_count).as_bytes())?;
+        let no_newline = b"\\ No newline at end of file";
+        self.print_line(&[b"-", old].concat())?;
+        if !newline {
+            self.print_line(no_newline)?;
+        }
+        for line in new_lines {
+            self.print_line(&[b"+", line].concat())?;
+        }
+        if !newline {
+            self.print_line(no_newline)?;
+        }
+        Ok(())
+    }
+
+    fn print_json(&mut self, value: Value) -> io::Result<()> {
+        self.print_line(value.to_string().as_bytes())
+    }
+
+    // A file only begins when it has something to report, like ripgrep
+    fn print_json_begin(&mut self, filename: &str) -> io::Result<()> {
+        if !self.begun {
+            self.begun = true;
+            self.print_json(json!({"type": "begin", "data": {"path": json_data(filename.as_bytes())}}))?;
+        }
+        Ok(())
+    }
+
+    fn print_json_event(&mut self, filename: &str, event: Event) -> MyResult<()> {
+        let (kind, line) = match event {
+            Event::Break => return Ok(()),
+            Event::Match(line) => ("match", line),
+            Event::Context(line) => ("context", line),
+        };
+        self.print_json_begin(filename)?;
+        let submatches: Vec<Value> = self.config.matcher.find_iter(line.text)?
+            .into_iter()
+            .map(|found| json!({"match": json_data(&line.text[found.clone()]), "start": found.start, "end": found.end}))
+            .collect();
+        if kind == "match" {
+            self.stats.matched_lines += 1;
+            self.stats.matches += submatches.len();
+        }
+        let mut text = line.text.to_vec();
+        if line.newline {
+            text.push(b'\n');
+        }
+        self.print_json(json!({
+            "type": kind,
+            "data": {
+                "path": json_data(filename.as_bytes()),
+                "lines": json_data(&text),
+                "line_number": line.number,
+                "absolute_offset": line.offset,
+                "submatches": submatches,
+            },
+        }))?;
+        Ok(())
+    }
+
+    fn print_json_end(&mut self, filename: &str, binary_offset: Option<usize>) -> io::Result<()> {
+        if self.begun {
+            self.stats.bytes_printed = self.printed;
+            let stats = self.stats.to_json();
+            self.print_json(json!({
+                "type": "end",
+                "data": {"path": json_data(filename.as_bytes()), "binary_offset": binary_offset, "stats": stats},
+            }))?;
+        }
+        Ok(())
+    }
+}
+
+// Text for JSON output: a string if it is UTF-8, or else base64 bytes
+fn json_data(text: &[u8]) -> Value {
+    match str::from_utf8(text) {
+        Ok(text) => json!({"text": text}),
+        Err(_) => json!({"bytes": BASE64_STANDARD.encode(text)}),
+    }
+}
+
+// What --json reports for each file searched and for all of them
+#[derive(Debug, Default, Clone, Copy)]
+struct Stats {
+    elapsed: Duration,
+    searches: usize,
+    searches_with_match: usize,
+    bytes_searched: usize,
+    bytes_printed: usize,
+    matched_lines: usize,
+    matches: usize,
+}
+
+impl Stats {
+    fn add(&mut self, other: &Stats) {
+        self.elapsed += other.elapsed;
+        self.searches += other.searches;
+        self.searches_with_match += other.searches_with_match;
+        self.bytes_searched += other.bytes_searched;
+        self.bytes_printed += other.bytes_printed;
+        self.matched_lines += other.matched_lines;
+        self.matches += other.matches;
+    }
+
+    fn to_json(self) -> Value {
+        json!({
+            "elapsed": json_duration(self.elapsed),
+            "searches": self.searches,
+            "searches_with_match": self.searches_with_match,
+            "bytes_searched": self.bytes_searched,
+            "bytes_printed": self.bytes_printed,
+            "matched_lines": self.matched_lines,
+            "matches": self.matches,
+        })
+    }
+}
+
+fn json_duration(duration: Duration) -> Value {
+    json!({
+        "secs": duration.as_secs(),
+        "nanos": duration.subsec_nanos(),
+        "human": format!("{:.6}s", duration.as_secs_f64()),
+    })
+}
+
+// Counts the bytes read through it
+struct Counted<R> {
+    inner: R,
+    bytes: usize,
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes = self.inner.read(buf)?;
+        self.bytes += bytes;
+        Ok(bytes)
+    }
+}
+
+// What searching one file produced, printed in the order files are found
+#[derive(Default)]
+struct Searched {
+    // What was left to print when the search ended
+    output: Vec<u8>,
+    separated: bool,
+    count: usize,
+    stats: Stats,
+    error: Option<String>,
+}
+
+// The standard output shared by the search threads. The file at the head
+// of the order prints to it as it is searched, and files searched out of
+// order wait in `pending` until every file before them is done.
+struct Output {
+    separator: Vec<u8>,
+    quiet: bool,
+    head: AtomicUsize,
+    done: AtomicBool,
+    state: Mutex<OutputState>,
+}
+
+#[derive(Default)]
+struct OutputState {
+    pending: HashMap<usize, Searched>,
+    printed_any: bool,
+    selected_any: bool,
+    failed: bool,
+    stats: Stats,
+    error: Option<io::Error>,
+}
+
+impl Output {
+    fn is_head(&self, index: usize) -> bool {
+        self.head.load(Ordering::Acquire) == index
+    }
+
+    fn is_done(&self) -> bool {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    // Prints output of the file at the head, after a separator if it
+    // starts a new group of context lines. Printing fails once, after
+    // which the threads stop and `run` reports the error.
+    fn print(&self, state: &mut OutputState, separated: &mut bool, output: &[u8]) -> io::Result<()> {
+        if output.is_empty() {
+            return Ok(());
+        }
+        let mut stdout = io::stdout().lock();
+        let mut printed = Ok(());
+        if mem::take(separated) && state.printed_any {
+            printed = stdout.write_all(&self.separator);
+        }
+        if let Err(e) = printed.and_then(|_| stdout.write_all(output)) {
+            self.done.store(true, Ordering::Relaxed);
+            let kind = e.kind();
+            state.error.get_or_insert(e);
+            return Err(kind.into());
+        }
+        state.printed_any = true;
+        Ok(())
+    }
+
+    // Takes what searching the file at `index` produced and finishes the
+    // files now at the head of the order
+    fn finish(&self, index: usize, searched: Searched) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.pending.insert(index, searched);
+        let mut head = self.head.load(Ordering::Acquire);
+        while let Some(mut searched) = state.pending.remove(&head) {
+            if let Some(e) = searched.error {
+                eprintln!("{}", e);
+                state.failed = true;
+            }
+            state.selected_any |= searched.count > 0;
+            state.stats.add(&searched.stats);
+            if self.quiet && state.selected_any {
+                self.done.store(true, Ordering::Relaxed);
+                return Ok(());
+            }
+            self.print(&mut state, &mut searched.separated, &searched.output)?;
+            head += 1;
+            self.head.store(head, Ordering::Release);
+        }
+        Ok(())
+    }
+}
+
+// Searches the files on a pool of threads fed from a queue of paths.
+// Returns the exit status: 0 if any line was selected, 1 if none was,
+// and 2 if a file could not be searched (unless -q found a match).
+pub fn run(config: Config) -> MyResult<i32> {
+    let started = Instant::now();
+    let show_filename = config.with_filename.unwrap_or(
+        config.files.len() > 1
+            || config.walk.recursive && config.files.iter().any(|f| Path::new(f).is_dir()),
+    );
+    let mut separator = match &config.colors {
+        Some(colors) => colors.paint(&colors.separator, b"--"),
+        None => b"--".to_vec(),
     };
+    separator.push(b'\n');
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
 
-    for entry in entries {
-        match entry {
-            Err(e) => eprintln!("{}", e),
-            Ok(filename) => match open(&filename) {
-                Err(e) => eprintln!("{}: {}", filename, e),
-                Ok(file) => {
-                    match find_lines(
-                    // match _find_lines(
-                        file,
-                        &config.pattern,
-                        config.invert_match,
-                    ) {
-                        Err(e) => eprintln!("{}", e),
-                        Ok(matches) => {
-                            if config.count {
-                                print(&filename, &format!("{}\n", matches.len()));
-                            } else {
-                                for line in &matches {
-                                        print(&filename, line);
-                                }
-                            }
-                        }
+    let config = &config;
+    let output = &Output {
+        separator,
+        quiet: config.quiet,
+        head: AtomicUsize::new(0),
+        done: AtomicBool::new(false),
+        state: Mutex::new(OutputState::default()),
+    };
+    let (path_tx, path_rx) = mpsc::channel();
+    let path_rx = &Mutex::new(path_rx);
+
+    thread::scope(|scope| {
+        // Paths are numbered as they are found to print results in order
+        scope.spawn(move || {
+            let mut index = 0;
+            find_files(&config.files, &config.walk, |entry| {
+                let sent = path_tx.send((index, entry)).is_ok();
+                index += 1;
+                sent && !output.is_done()
+            });
+        });
+
+        for _ in 0..threads {
+            scope.spawn(move || {
+                loop {
+                    let next = path_rx.lock().unwrap().recv();
+                    let Ok((index, entry)) = next else {
+                        break;
+                    };
+                    let searched = match entry {
+                        Ok(filename) => search_file(config, &filename, show_filename, output, index),
+                        Err(e) => Searched { error: Some(e), ..Default::default() },
+                    };
+                    if output.is_done() || output.finish(index, searched).is_err() {
+                        break;
                     }
                 }
+            });
+        }
+    });
+
+    let state = output.state.lock().unwrap();
+    if let Some(e) = &state.error {
+        return Err(e.to_string().into());
+    }
+    if config.json {
+        let summary = json!({
+            "type": "summary",
+            "data": {"elapsed_total": json_duration(started.elapsed()), "stats": state.stats.to_json()},
+        });
+        writeln!(io::stdout(), "{}", summary)?;
+    }
+
+    Ok(if config.quiet && state.selected_any {
+        0
+    } else if state.failed {
+        2
+    } else if state.selected_any {
+        0
+    } else {
+        1
+    })
+}
+
+fn search_file(config: &Config, filename: &str, show_filename: bool, output: &Output, index: usize) -> Searched {
+    let mut printer = Printer::new(config, show_filename, output, index);
+    let found = if config.in_place {
+        rewrite(config, filename, &mut printer).map_err(|e| format!("{}: {}", filename, e))
+    } else {
+        open(filename, config.search_zip)
+            .map_err(|e| format!("{}: {}", filename, e))
+            .and_then(|file| search(config, file, filename, &mut printer).map_err(|e| format!("{}: {}", filename, e)))
+    };
+    let (count, error) = match found {
+        Ok(count) => (count, None),
+        Err(e) => (0, Some(e)),
+    };
+    Searched {
+        output: printer.out,
+        separated: printer.separated,
+        count,
+        stats: printer.stats,
+        error,
+    }
+}
+
+fn search(config: &Config, mut file: Box<dyn Read>, filename: &str, printer: &mut Printer) -> MyResult<usize> {
+    let started = Instant::now();
+    // Binary files, with a NUL in the first block, only tell if they match
+    let mut head = vec![0; BLOCK_SIZE];
+    let bytes = file.read(&mut head)?;
+    head.truncate(bytes);
+    let binary_offset = memchr(0, &head).filter(|_| !config.text);
+    let binary = binary_offset.is_some();
+    let mut file = Counted { inner: io::Cursor::new(head).chain(file), bytes: 0 };
+    let list_files = config.files_with_matches || config.files_without_match;
+    let print_lines = !(config.count || list_files || config.quiet || binary);
+
+    // Whether a file matches at all only needs its first selected line
+    let max_count = if print_lines || config.count {
+        config.max_count
+    } else {
+        Some(config.max_count.unwrap_or(1).min(1))
+    };
+    // Only whole lines are printed with context
+    let context = if print_lines && (config.json || !config.only_matching) {
+        (config.before_context, config.after_context)
+    } else {
+        (0, 0)
+    };
+
+    let count = find_lines(&mut file, &config.matcher, config.invert_match, context, max_count, |event| {
+        if print_lines {
+            printer.print_event(filename, event)?;
+        }
+        Ok(())
+    })?;
+
+    if config.quiet {
+        return Ok(count);
+    }
+    if config.json {
+        if binary && count > 0 {
+            printer.print_json_begin(filename)?;
+            printer.stats.matched_lines = count;
+        }
+        printer.stats.elapsed = started.elapsed();
+        printer.stats.searches = 1;
+        printer.stats.searches_with_match = usize::from(count > 0);
+        printer.stats.bytes_searched = file.bytes;
+        printer.print_json_end(filename, binary_offset)?;
+    } else if list_files {
+        if (count > 0) == config.files_with_matches {
+            printer.print_filename(filename)?;
+        }
+    } else if config.count {
+        printer.print_count(filename, count)?;
+    } else if binary && count > 0 {
+        printer.print_line(format!("Binary file {} matches", filename).as_bytes())?;
+    }
+    Ok(count)
+}
+
+// Makes the replacements in the selected lines of a file, or with
+// --dry-run only prints them as a diff. Returns the number of lines
+// selected.
+fn rewrite(config: &Config, filename: &str, printer: &mut Printer) -> MyResult<usize> {
+    if filename == "-" {
+        return Err("Cannot rewrite standard input".into());
+    }
+    let content = fs::read(filename)?;
+    // Binary files are left alone, as searching them prints no lines
+    if !config.text && memchr(0, &content[..content.len().min(BLOCK_SIZE)]).is_some() {
+        return Ok(0);
+    }
+
+    let replacement = config.replace.as_deref().unwrap_or_default();
+    let mut rewritten = vec![];
+    let mut last = 0;
+    let mut added_lines = 0;
+    let count = find_lines(&content[..], &config.matcher, false, (0, 0), config.max_count, |event| {
+        let Event::Match(line) = event else {
+            return Ok(());
+        };
+        let new = config.matcher.replace(line.text, replacement)?;
+        if new != line.text {
+            let end = line.offset + line.text.len();
+            if config.dry_run {
+                let numbers = (line.number, line.number + added_lines);
+                printer.print_diff(filename, numbers, line.text, &new, end < content.len())?;
             }
+            added_lines += memchr_iter(b'\n', &new).count();
+            rewritten.extend(&content[last..line.offset]);
+            rewritten.extend(&new);
+            last = end;
         }
+        Ok(())
+    })?;
+
+    if last > 0 && !config.dry_run {
+        rewritten.extend(&content[last..]);
+        write_atomically(Path::new(filename), &rewritten)?;
     }
+    Ok(count)
+}
 
-    Ok(())
+// Writes a file beside `path` and renames it over, so that `path` never
+// has partial contents
+fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.grepr-{}", name, process::id()));
+    let written = File::create_new(&temp).and_then(|mut file| {
+        file.write_all(content)?;
+        file.set_permissions(fs::metadata(path)?.permissions())?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    });
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+fn open(filename: &str, search_zip: bool) -> MyResult<Box<dyn Read>> {
+    let file: Box<dyn Read> = match filename {
+        "-" => Box::new(io::stdin()),
+        _ => Box::new(File::open(filename)?),
+    };
+    if search_zip {
+        decompress(file)
+    } else {
+        Ok(file)
     }
 }
 
-fn find_files(paths: &[String], recursive: bool) -> Vec<MyResult<String>> {
-    let mut results = vec![];
+// Recognizes a compressed stream by its magic bytes and reads it
+// decompressed, passing anything else through unchanged
+fn decompress(mut file: Box<dyn Read>) -> MyResult<Box<dyn Read>> {
+    // A pipe can give the magic bytes a few at a time
+    let mut head = vec![];
+    file.by_ref().take(6).read_to_end(&mut head)?;
+    let file = BufReader::new(io::Cursor::new(head.clone()).chain(file));
+    Ok(if head.starts_with(b"\x1f\x8b") {
+        Box::new(MultiGzDecoder::new(file))
+    } else if head.starts_with(b"BZh") {
+        Box::new(MultiBzDecoder::new(file))
+    } else if head.starts_with(b"\xfd7zXZ\x00") {
+        Box::new(XzDecoder::new_multi_decoder(file))
+    } else if head.starts_with(b"\x28\xb5\x2f\xfd") {
+        Box::new(zstd::Decoder::with_buffer(file)?)
+    } else {
+        Box::new(file)
+    })
+}
+
+impl Walk {
+    // Whether --include and --exclude let a file be searched
+    fn selects(&self, path: &Path) -> bool {
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        (self.include.is_empty() || self.include.is_match(name)) && !self.exclude.is_match(name)
+    }
+}
 
+// Passes each file to search in `paths`, or why it cannot be, to `emit`
+// as soon as it is found, stopping when `emit` returns false
+fn find_files(paths: &[String], walk: &Walk, mut emit: impl FnMut(Result<String, String>) -> bool) {
     for path in paths {
-        match path.as_str() {
-            "-" => results.push(Ok(path.to_string())),
+        let more = match path.as_str() {
+            "-" => emit(Ok(path.to_string())),
             _ => match fs::metadata(path) {
-                Ok(metadata) => {
-                    if metadata.is_dir() {
-                        if recursive {
-                          for entry in WalkDir::new(path)
-                              .into_iter()
-                              .flatten()
-                              .filter(|e| e.file_type().is_file())
-                          {
-                              results.push(Ok(entry
-                                  .path()
-                                  .display()
-                                  .to_string()));
-                          }
-                        } else {
-                            results.push(Err(From::from(format!("{} is a directory", path))));
-                        }
-                    } else if metadata.is_file() {
-                        results.push(Ok(path.to_string()));
+                Ok(metadata) if metadata.is_dir() => {
+                    if walk.recursive {
+                        walk_dir(path, walk, &mut emit)
+                    } else {
+                        emit(Err(format!("{} is a directory", path)))
                     }
                 }
-                Err(e) => {
-                    results.push(Err(From::from(format!("{}: {} ", path, e))));
+                Ok(metadata) if metadata.is_file() && walk.selects(Path::new(path)) => {
+                    emit(Ok(path.to_string()))
                 }
-            }
+                Ok(_) => true,
+                Err(e) => emit(Err(format!("{}: {} ", path, e))),
+            },
+        };
+        if !more {
+            return;
         }
     }
+}
+
+fn walk_dir(path: &str, walk: &Walk, emit: &mut impl FnMut(Result<String, String>) -> bool) -> bool {
+    let exclude_dir = walk.exclude_dir.clone();
+    let entries = WalkBuilder::new(path)
+        .standard_filters(false)
+        .hidden(false)
+        .git_ignore(walk.gitignore)
+        .parents(walk.gitignore)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            entry.depth() == 0 || !is_dir || !exclude_dir.is_match(entry.file_name())
+        })
+        .build();
 
-    results
+    for entry in entries {
+        let more = match entry {
+            Ok(entry) if entry.file_type().is_some_and(|t| t.is_file()) && walk.selects(entry.path()) => {
+                emit(Ok(entry.path().display().to_string()))
+            }
+            Ok(_) => true,
+            Err(e) => emit(Err(e.to_string())),
+        };
+        if !more {
+            return false;
+        }
+    }
+    true
 }
 
-fn find_lines<T: BufRead>(mut file: T, pattern: &Regex, invert_match: bool) -> MyResult<Vec<String>> {
-    let mut matches = vec![];
-    let mut lines = String::new();
+// Streams the selected lines of `file` to `emit`, along with up to
+// `before` and `after` lines of context around each, and returns the
+// number of selected lines. Reading stops once `max_count` lines have
+// been selected and their trailing context emitted.
+fn find_lines(
+    mut file: impl Read,
+    matcher: &Matcher,
+    invert_match: bool,
+    (before, after): (usize, usize),
+    max_count: Option<usize>,
+    mut emit: impl FnMut(Event) -> MyResult<()>,
+) -> MyResult<usize> {
+    let mut finder = LineFinder {
+        matcher,
+        invert_match,
+        before,
+        after,
+        max_count,
+        count: 0,
+        number: 0,
+        offset: 0,
+        leading: VecDeque::with_capacity(before),
+        trailing: 0,
+        last_emitted: None,
+    };
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut filled = 0;
 
-    loop {
-        let bytes = file.read_line(&mut lines)?;
+    while !finder.done() {
+        if filled == buf.len() {
+            buf.resize(2 * buf.len(), 0);
+        }
+        let bytes = match file.read(&mut buf[filled..]) {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            result => result?,
+        };
+        filled += bytes;
+
+        // Only whole lines are searched, the rest waits for more input
+        let end = if bytes == 0 {
+            filled
+        } else {
+            match memrchr(b'\n', &buf[filled - bytes..filled]) {
+                Some(i) => filled - bytes + i + 1,
+                None => continue,
+            }
+        };
+        finder.find(&buf[..end], &mut emit)?;
+        buf.copy_within(end..filled, 0);
+        filled -= end;
         if bytes == 0 {
             break;
         }
-        if pattern.is_match(&lines) ^ invert_match {
-            matches.push(mem::take(&mut lines));
-        }
-        lines.clear();
     }
-    Ok(matches)
+    Ok(finder.count)
 }
-fn _find_lines<T: BufRead>(mut file: T, pattern: &Regex, invert_match: bool) -> MyResult<Vec<String>> {
-    let mut it = file.lines().peekable();
-    let mut matches = vec![];
-    while let Some(line) = it.next() {
-        let is_last = it.peek().is_none();
 
-        let line = line?;
-        // Do not need this break - we take every line that is read from file
-        // if line.len() == 0 {
-        //     break;
-        // }
+// Counts the lines of `input` that match `pattern`, like -c, for the
+// benchmarks
+#[doc(hidden)]
+pub fn count_matching_lines(pattern: &str, input: impl Read) -> MyResult<usize> {
+    let matcher = Matcher::new(&[pattern.to_string()], Syntax::Extended, false, false, false)?;
+    find_lines(input, &matcher, false, (0, 0), None, |_| Ok(()))
+}
 
-        if pattern.is_match(&line) ^ invert_match {
-            matches.push(line);
+// The state of selecting lines carried from one chunk of a file to the next
+struct LineFinder<'a> {
+    matcher: &'a Matcher,
+    invert_match: bool,
+    before: usize,
+    after: usize,
+    max_count: Option<usize>,
+    count: usize,
+    number: usize,
+    offset: usize,
+    leading: VecDeque<(usize, usize, Vec<u8>)>,
+    trailing: usize,
+    last_emitted: Option<usize>,
+}
+
+impl LineFinder<'_> {
+    fn limit_reached(&self) -> bool {
+        self.max_count.is_some_and(|max| self.count >= max)
+    }
+
+    // Past the maximum, only the trailing context is still read
+    fn done(&self) -> bool {
+        self.limit_reached() && self.trailing == 0
+    }
+
+    // Selects lines from `chunk`, which ends at the end of a line or file
+    fn find(&mut self, chunk: &[u8], emit: &mut impl FnMut(Event) -> MyResult<()>) -> MyResult<()> {
+        let mut pos = 0;
+        let mut next = self.matcher.find_line(chunk, 0)?;
+
+        while pos < chunk.len() && !self.done() {
+            // Lines that are neither selected nor context are skipped
+            // without looking at them
+            if !self.invert_match && self.trailing == 0 {
+                let target = next.as_ref().map_or(chunk.len(), |found| found.start);
+                let skip_to = lines_back(chunk, pos, target, self.before);
+                if skip_to > pos {
+                    self.number += memchr_iter(b'\n', &chunk[pos..skip_to]).count();
+                    self.offset += skip_to - pos;
+                    self.leading.clear();
+                    pos = skip_to;
+                }
+                if pos == chunk.len() {
+                    break;
+                }
+            }
+
+            let end = memchr(b'\n', &chunk[pos..]).map_or(chunk.len(), |i| pos + i + 1);
+            let is_match = next.as_ref().is_some_and(|found| found.start == pos);
+            if is_match {
+                next = self.matcher.find_line(chunk, end)?;
+            }
+            self.number += 1;
+            self.select(&chunk[pos..end], is_match, emit)?;
+            self.offset += end - pos;
+            pos = end;
+        }
+        Ok(())
+    }
+
+    fn select(&mut self, line: &[u8], is_match: bool, emit: &mut impl FnMut(Event) -> MyResult<()>) -> MyResult<()> {
+        let (number, offset) = (self.number, self.offset);
+        let text = line.strip_suffix(b"\n").unwrap_or(line);
+        let newline = text.len() < line.len();
+        if !self.limit_reached() && is_match ^ self.invert_match {
+            self.count += 1;
+            let first = self.leading.front().map_or(number, |(num, _, _)| *num);
+            let adjacent = self.last_emitted.is_some_and(|last| last + 1 == first);
+            if (self.before > 0 || self.after > 0) && !adjacent {
+                emit(Event::Break)?;
+            }
+            for (number, offset, text) in self.leading.drain(..) {
+                emit(Event::Context(Line { number, offset, text: &text, newline: true }))?;
+            }
+            emit(Event::Match(Line { number, offset, text, newline }))?;
+            self.last_emitted = Some(number);
+            self.trailing = self.after;
+        } else if self.trailing > 0 {
+            emit(Event::Context(Line { number, offset, text, newline }))?;
+            self.last_emitted = Some(number);
+            self.trailing -= 1;
+        } else if self.before > 0 {
+            if self.leading.len() == self.before {
+                self.leading.pop_front();
+            }
+            self.leading.push_back((number, offset, text.to_vec()));
         }
+        Ok(())
     }
+}
 
-    Ok(matches)
+// Returns where the line `count` lines before the one at `target` starts,
+// but not before `floor`
+fn lines_back(buf: &[u8], floor: usize, target: usize, count: usize) -> usize {
+    let mut start = target;
+    for _ in 0..count {
+        if start <= floor {
+            return floor;
+        }
+        start = memrchr(b'\n', &buf[floor..start - 1]).map_or(floor, |i| floor + i + 1);
+    }
+    start.max(floor)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rand::{distr::Alphanumeric, Rng};
+
+    fn walk(recursive: bool, gitignore: bool, include: &[&str], exclude: &[&str], exclude_dir: &[&str]) -> Walk {
+        let globs = |globs: &[&str]| {
+            let mut builder = GlobSetBuilder::new();
+            for glob in globs {
+                builder.add(Glob::new(glob).unwrap());
+            }
+            builder.build().unwrap()
+        };
+        Walk {
+            recursive,
+            gitignore,
+            include: globs(include),
+            exclude: globs(exclude),
+            exclude_dir: globs(exclude_dir),
+        }
+    }
+
+    fn collect_files(path: &str, walk: &Walk) -> Vec<Result<String, String>> {
+        let mut files = vec![];
+        find_files(&[path.to_string()], walk, |entry| {
+            files.push(entry);
+            true
+        });
+        files
+    }
 
     #[test]
     fn test_find_files() {
-        let files = find_files(&[String::from("tests/inputs/fox.txt")], false);
-        assert_eq!(files.len(), 1);
-        assert_eq!(files[0].as_ref().unwrap(), "tests/inputs/fox.txt");
+        let files = collect_files("tests/inputs/fox.txt", &walk(false, false, &[], &[], &[]));
+        assert_eq!(files, [Ok("tests/inputs/fox.txt".to_string())]);
+
+        let files = collect_files("tests/tree", &walk(false, false, &[], &[], &[]));
+        assert_eq!(files, [Err("tests/tree is a directory".to_string())]);
+
+        let files = collect_files("tests/tree", &walk(true, false, &[], &[], &[]));
+        assert_eq!(
+            files,
+            [
+                Ok("tests/tree/.gitignore".to_string()),
+                Ok("tests/tree/app.log".to_string()),
+                Ok("tests/tree/build/out.txt".to_string()),
+                Ok("tests/tree/data.bin".to_string()),
+                Ok("tests/tree/notes.txt".to_string()),
+                Ok("tests/tree/src/main.txt".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_files_filtered() {
+        let names = |walk: &Walk| -> Vec<String> {
+            collect_files("tests/tree", walk).into_iter().map(Result::unwrap).collect()
+        };
+
+        let files = names(&walk(true, true, &[], &[], &[]));
+        assert_eq!(
+            files,
+            [
+                "tests/tree/.gitignore",
+                "tests/tree/data.bin",
+                "tests/tree/notes.txt",
+                "tests/tree/src/main.txt",
+            ]
+        );
+
+        let files = names(&walk(true, false, &["*.txt"], &[], &["build"]));
+        assert_eq!(files, ["tests/tree/notes.txt", "tests/tree/src/main.txt"]);
+
+        let files = names(&walk(true, false, &[], &["*.txt", ".*"], &[]));
+        assert_eq!(files, ["tests/tree/app.log", "tests/tree/data.bin"]);
+
+        // Only directories met while recursing are excluded
+        let files = names(&walk(true, false, &["*.txt"], &[], &["tree"]));
+        assert_eq!(
+            files,
+            ["tests/tree/build/out.txt", "tests/tree/notes.txt", "tests/tree/src/main.txt"]
+        );
+    }
+
+    fn collect_lines(
+        text: &str,
+        pattern: &str,
+        invert_match: bool,
+        context: (usize, usize),
+        max_count: Option<usize>,
+    ) -> (usize, Vec<String>) {
+        let re = Matcher::Regex(Regex::new(pattern).unwrap());
+        let mut lines = vec![];
+        let count = find_lines(io::Cursor::new(text), &re, invert_match, context, max_count, |event| {
+            lines.push(match event {
+                Event::Match(line) => format!("{}:{}", line.number, String::from_utf8_lossy(line.text)),
+                Event::Context(line) => format!("{}-{}", line.number, String::from_utf8_lossy(line.text)),
+                Event::Break => "--".to_string(),
+            });
+            Ok(())
+        })
+        .unwrap();
+        (count, lines)
     }
 
     #[test]
     fn test_find_lines() {
+        let text = "Lorem\nIpsum\r\nDOLOR";
+
+        let (count, lines) = collect_lines(text, "or", false, (0, 0), None);
+        assert_eq!(count, 1);
+        assert_eq!(lines, ["1:Lorem"]);
+
+        let (count, lines) = collect_lines(text, "or", true, (0, 0), None);
+        assert_eq!(count, 2);
+        assert_eq!(lines, ["2:Ipsum\r", "3:DOLOR"]);
+
+        let (count, lines) = collect_lines(text, "(?i)or", false, (0, 0), None);
+        assert_eq!(count, 2);
+        assert_eq!(lines, ["1:Lorem", "3:DOLOR"]);
+    }
+
+    #[test]
+    fn test_find_lines_context() {
+        let text = "1\n2\nx3\n4\n5\n6\nx7\n8\nx9\n10\n11\n12\n";
+
+        let (count, lines) = collect_lines(text, "x", false, (1, 0), None);
+        assert_eq!(count, 3);
+        assert_eq!(lines, ["--", "2-2", "3:x3", "--", "6-6", "7:x7", "8-8", "9:x9"]);
+
+        let (_, lines) = collect_lines(text, "x", false, (0, 1), None);
+        assert_eq!(lines, ["--", "3:x3", "4-4", "--", "7:x7", "8-8", "9:x9", "10-10"]);
+
+        // Overlapping windows are merged, adjacent ones are not separated
+        let (_, lines) = collect_lines(text, "x", false, (2, 2), None);
+        assert_eq!(
+            lines,
+            ["--", "1-1", "2-2", "3:x3", "4-4", "5-5", "6-6", "7:x7", "8-8", "9:x9", "10-10", "11-11"]
+        );
+
+        // Context at the edges of the input is cut short
+        let (_, lines) = collect_lines("x1\n2\n3", "x", false, (5, 5), None);
+        assert_eq!(lines, ["--", "1:x1", "2-2", "3-3"]);
+
+        // No separators without context
+        let (_, lines) = collect_lines(text, "x", false, (0, 0), None);
+        assert_eq!(lines, ["3:x3", "7:x7", "9:x9"]);
+    }
+
+    #[test]
+    fn test_find_lines_max_count() {
+        let text = "a1\nb\na2\na3\nc\na4\n";
+
+        let (count, lines) = collect_lines(text, "a", false, (0, 0), Some(2));
+        assert_eq!(count, 2);
+        assert_eq!(lines, ["1:a1", "3:a2"]);
+
+        let (count, lines) = collect_lines(text, "a", false, (0, 0), Some(0));
+        assert_eq!(count, 0);
+        assert!(lines.is_empty());
+
+        // Matches in the trailing context are printed as context
+        let (count, lines) = collect_lines(text, "a", false, (0, 3), Some(2));
+        assert_eq!(count, 2);
+        assert_eq!(lines, ["--", "1:a1", "2-b", "3:a2", "4-a3", "5-c", "6-a4"]);
+
+        let (count, _) = collect_lines(text, "a", true, (0, 0), Some(1));
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_find_lines_offsets() {
+        let re = Matcher::Regex(Regex::new("b").unwrap());
+        let mut offsets = vec![];
+        find_lines(io::Cursor::new("a\nbé\nc\nb"), &re, false, (1, 0), None, |event| {
+            if let Event::Match(line) | Event::Context(line) = event {
+                offsets.push((line.number, line.offset));
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(offsets, [(1, 0), (2, 2), (3, 6), (4, 8)]);
+    }
+
+    // Hands out its bytes a few at a time, like a slow pipe
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let bytes = self.0.len().min(buf.len()).min(3);
+            buf[..bytes].copy_from_slice(&self.0[..bytes]);
+            self.0 = &self.0[bytes..];
+            Ok(bytes)
+        }
+    }
+
+    fn collect_bytes(input: impl Read, pattern: &str, context: (usize, usize)) -> Vec<(usize, usize, Vec<u8>)> {
+        let re = Matcher::Regex(Regex::new(pattern).unwrap());
+        let mut lines = vec![];
+        find_lines(input, &re, false, context, None, |event| {
+            if let Event::Match(line) | Event::Context(line) = event {
+                lines.push((line.number, line.offset, line.text.to_vec()));
+            }
+            Ok(())
+        })
+        .unwrap();
+        lines
+    }
+
+    #[test]
+    fn test_find_lines_bytes() {
+        // Invalid UTF-8 is searched and passed on as it is
+        let lines = collect_bytes(&b"caf\xe9\nfox \xff\n"[..], "fox", (0, 0));
+        assert_eq!(lines, [(2, 5, b"fox \xff".to_vec())]);
+
+        // Lines are whole however the input is split
+        let text = b"ab\nfox\n\nfoxes\nno\nfox";
+        let lines = collect_bytes(Trickle(text), "^fox", (1, 0));
+        assert_eq!(
+            lines,
+            [
+                (1, 0, b"ab".to_vec()),
+                (2, 3, b"fox".to_vec()),
+                (3, 7, b"".to_vec()),
+                (4, 8, b"foxes".to_vec()),
+                (5, 14, b"no".to_vec()),
+                (6, 17, b"fox".to_vec()),
+            ]
+        );
+
+        // A line longer than a chunk is read whole
+        let mut text = vec![b'x'; 3 * CHUNK_SIZE];
+        text.extend(b"fox\nfox\n");
+        let lines = collect_bytes(&text[..], "fox", (0, 0));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].2.len(), 3 * CHUNK_SIZE + 3);
+        assert_eq!(lines[1].0, 2);
+        assert_eq!(lines[1].1, 3 * CHUNK_SIZE + 4);
+    }
+
+    #[test]
+    fn test_find_line() {
+        let re = Matcher::Regex(Regex::new(r"a\sb").unwrap());
+        // The first candidate spans a newline and is not a match
+        let buf = b"xa\nb\ny a b\nab";
+        assert_eq!(re.find_line(buf, 0).unwrap(), Some(5..11));
+        assert_eq!(re.find_line(buf, 11).unwrap(), None);
+
+        let re = Matcher::Perl(fancy_regex::Regex::new(r"b(?=\w)").unwrap());
+        assert_eq!(re.find_line(b"ab\nbc\n", 0).unwrap(), Some(3..6));
+
+        assert_eq!(lines_back(b"1\n2\n3\n4\n", 0, 6, 2), 2);
+        assert_eq!(lines_back(b"1\n2\n3\n4\n", 4, 6, 2), 4);
+        assert_eq!(lines_back(b"1\n2\n3", 0, 5, 1), 4);
+    }
+
+    fn matches(matcher: &Matcher, text: &str) -> Vec<String> {
+        let text = text.as_bytes();
+        matcher.find_iter(text).unwrap().into_iter().map(|m| String::from_utf8_lossy(&text[m]).into_owned()).collect()
+    }
+
+    #[test]
+    fn test_matcher() {
+        let patterns = |list: &[&str]| list.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let text = "a.b ab cat Catalog";
+
+        let matcher = Matcher::new(&patterns(&["a.b", "cat"]), Syntax::Fixed, false, false, false).unwrap();
+        assert!(matches!(matcher, Matcher::Literals(_)));
+        assert_eq!(matches(&matcher, text), ["a.b", "cat"]);
+
+        let matcher = Matcher::new(&patterns(&["cat"]), Syntax::Fixed, true, false, false).unwrap();
+        assert_eq!(matches(&matcher, text), ["cat", "Cat"]);
+
+        let matcher = Matcher::new(&patterns(&["a.b", "cat"]), Syntax::Extended, false, false, false).unwrap();
+        assert_eq!(matches(&matcher, text), ["a.b", "cat"]);
+
+        let matcher = Matcher::new(&patterns(&["cat"]), Syntax::Extended, true, true, false).unwrap();
+        assert_eq!(matches(&matcher, text), ["cat"]);
+
+        let matcher = Matcher::new(&patterns(&["a.b"]), Syntax::Fixed, false, true, false).unwrap();
+        assert!(matches!(matcher, Matcher::Regex(_)));
+        assert_eq!(matches(&matcher, text), ["a.b"]);
+
+        let matcher = Matcher::new(&patterns(&["a", "Cat.*"]), Syntax::Extended, false, false, true).unwrap();
+        assert!(!matcher.is_match(text.as_bytes()).unwrap());
+        assert!(matcher.is_match(b"Catalog").unwrap());
+
+        let matcher = Matcher::new(&patterns(&[r"\w+(?= Cat)", "ab"]), Syntax::Perl, false, true, false).unwrap();
+        assert_eq!(matches(&matcher, text), ["ab", "cat"]);
+
+        let matcher = Matcher::new(&patterns(&[r"\(a\|c\).\{2\}"]), Syntax::Basic, false, false, false).unwrap();
+        assert_eq!(matches(&matcher, text), ["a.b", "ab ", "cat", "ata"]);
+
+        // An empty pattern matches every line, no patterns match none
+        let matcher = Matcher::new(&patterns(&["", "x"]), Syntax::Extended, false, false, false).unwrap();
+        assert!(matcher.is_match(text.as_bytes()).unwrap());
+        let matcher = Matcher::new(&[], Syntax::Extended, false, false, false).unwrap();
+        assert!(!matcher.is_match(text.as_bytes()).unwrap());
+
+        let res = Matcher::new(&patterns(&["a", "*b"]), Syntax::Extended, false, false, false);
+        assert_eq!(res.unwrap_err().to_string(), r#"Invalid pattern "*b""#);
+    }
+
+    #[test]
+    fn test_replace() {
+        let patterns = |list: &[&str]| list.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let text = b"a.b ab cat Catalog";
+
+        let matcher = Matcher::new(&patterns(&[r"(\w)a(?<end>\w)"]), Syntax::Extended, false, false, false).unwrap();
+        assert_eq!(matcher.replace(text, "${end}$1").unwrap(), b"a.b ab tc tCalog");
+        let matcher = Matcher::new(&patterns(&["cat"]), Syntax::Fixed, true, false, false).unwrap();
+        assert_eq!(matcher.replace(text, "<$0>").unwrap(), b"a.b ab <cat> <Cat>alog");
+        let matcher = Matcher::new(&patterns(&[r"(?<w>\w+)(?= Cat)"]), Syntax::Perl, false, false, false).unwrap();
+        assert_eq!(matcher.replace(text, "${w}s").unwrap(), b"a.b ab cats Catalog");
+
+        let found = matcher.replacements(text, "$1").unwrap();
+        assert_eq!(found, [(7..10, b"cat".to_vec())]);
+    }
+
+    #[test]
+    fn test_expand_literal() {
+        // The same as the regex crate with only the whole match
+        let caps = Regex::new("ab").unwrap().captures(b"xaby").unwrap();
+        for replacement in ["$0", "${0}", "<$0$0>", "$$0", "$1", "$x", "${x}y", "$", "${}", "${0", "a$ b"] {
+            let mut expected = vec![];
+            caps.expand(replacement.as_bytes(), &mut expected);
+            assert_eq!(expand_literal(replacement, b"ab"), expected, "{}", replacement);
+        }
+    }
+
+    #[test]
+    fn test_bre_to_ere() {
+        assert_eq!(bre_to_ere("a+b?c|d(e){2}"), r"a\+b\?c\|d\(e\)\{2\}");
+        assert_eq!(bre_to_ere(r"\(ab\)\{1,2\}\|c\+"), "(ab){1,2}|c+");
+        assert_eq!(bre_to_ere("*a*"), r"\*a*");
+        assert_eq!(bre_to_ere(r"^*\(*x\|*\)"), r"^\*(\*x|\*)");
+        assert_eq!(bre_to_ere(r"\.\*\<x\>"), r"\.\*\<x\>");
+        assert_eq!(bre_to_ere(r"[]a[:digit:]\]"), r"[\]a[:digit:]\\]");
+        assert_eq!(bre_to_ere("[^]&~[]"), r"[^\]\&\~\[]");
+        for pattern in ["[]a[:digit:]\\]", "[^]&~[]", "a\\{2\\}"] {
+            assert!(Regex::new(&bre_to_ere(pattern)).is_ok(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_parse_colors() {
+        let default = Colors::parse("");
+        assert_eq!(default.selected_match, "01;31");
+        assert_eq!(default.filename, "35");
+        assert_eq!(default.separator, "36");
+        assert!(default.erase_line);
+
+        let colors = Colors::parse("mt=01;32:fn=:ln=33:sl=1:rv:ne:xx=9");
+        assert_eq!(colors.selected_match, "01;32");
+        assert_eq!(colors.context_match, "01;32");
+        assert_eq!(colors.filename, "");
+        assert_eq!(colors.line_number, "33");
+        assert_eq!(colors.selected_line, "1");
+        assert!(colors.reverse);
+        assert!(!colors.erase_line);
 
+        assert_eq!(colors.paint("33", b"12"), b"\x1b[33m12\x1b[m");
+        assert_eq!(colors.paint("", b"12"), b"12");
+        assert_eq!(default.paint("35", b"f"), b"\x1b[35m\x1b[Kf\x1b[m\x1b[K");
     }
 }
\ No newline at end of file
@@ -1,6 +1,9 @@
 fn main() {
-    if let Err(e) = ch9_grepr::get_args().and_then(ch9_grepr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
+    match ch9_grepr::get_args().and_then(ch9_grepr::run) {
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
     }
 }
@@ -14,7 +14,6 @@ const INPUTS_DIR: &str = "tests/inputs";
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
-// Some tests fail due to that `grep` put a new line at the end of a file
 // --------------------------------------------------
 fn gen_bad_file() -> String {
     loop {
@@ -65,6 +64,11 @@ fn warns_bad_file() -> TestResult {
 
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> TestResult {
+    run_status(args, expected_file, 0)
+}
+
+// --------------------------------------------------
+fn run_status(args: &[&str], expected_file: &str, status: i32) -> TestResult {
     let windows_file = format!("{expected_file}.windows");
     let expected_file = if os_type().unwrap() == "Windows"
         && Path::new(&windows_file).is_file()
@@ -76,6 +80,21 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
 
     let expected = fs::read_to_string(expected_file)?;
     let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
+    assert_eq!(output.status.code(), Some(status));
+
+    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
+    assert_eq!(stdout, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_colors(args: &[&str], grep_colors: &str, expected_file: &str) -> TestResult {
+    let expected = fs::read_to_string(expected_file)?;
+    let output = Command::cargo_bin(PRG)?
+        .args(args)
+        .env("GREP_COLORS", grep_colors)
+        .output()
+        .expect("fail");
     assert!(output.status.success());
 
     let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
@@ -83,10 +102,40 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+// Compares JSON Lines output without the elapsed times, which vary
+fn run_json(args: &[&str], expected_file: &str) -> TestResult {
+    let parse = |text: &str| -> Vec<serde_json::Value> {
+        text.lines()
+            .map(|line| {
+                let mut value: serde_json::Value = serde_json::from_str(line).expect("invalid JSON");
+                let data = value["data"].as_object_mut().unwrap();
+                data.remove("elapsed_total");
+                if let Some(stats) = data.get_mut("stats") {
+                    stats.as_object_mut().unwrap().remove("elapsed");
+                }
+                value
+            })
+            .collect()
+    };
+    let expected = fs::read_to_string(expected_file)?;
+    let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
+    assert_eq!(parse(&stdout), parse(&expected));
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn empty_file() -> TestResult {
-    run(&["foo", EMPTY], "tests/expected/empty.foo.txt")
+    Command::cargo_bin(PRG)?
+        .args(["foo", EMPTY])
+        .assert()
+        .code(1)
+        .stdout("");
+    Ok(())
 }
 
 // --------------------------------------------------
@@ -122,7 +171,7 @@ fn bustle_insensitive() -> TestResult {
 // --------------------------------------------------
 #[test]
 fn nobody() -> TestResult {
-    run(&["nobody", NOBODY], "tests/expected/nobody.txt")
+    run_status(&["nobody", NOBODY], "tests/expected/nobody.txt", 1)
 }
 
 // --------------------------------------------------
@@ -200,7 +249,7 @@ fn insensitive_count() -> TestResult {
 // --------------------------------------------------
 #[test]
 fn nobody_count() -> TestResult {
-    run(&["-c", "nobody", NOBODY], "tests/expected/nobody.txt.count")
+    run_status(&["-c", "nobody", NOBODY], "tests/expected/nobody.txt.count", 1)
 }
 
 // --------------------------------------------------
@@ -286,4 +335,558 @@ fn stdin_insensitive_count() -> TestResult {
     let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
     assert_eq!(stdout, expected);
     Ok(())
-}
\ No newline at end of file
+}
+// --------------------------------------------------
+#[test]
+fn after_context() -> TestResult {
+    run(
+        &["-A", "1", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized.A1",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn before_context() -> TestResult {
+    run(
+        &["--before-context", "2", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.lowercase.B2",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn context_insensitive() -> TestResult {
+    run(
+        &["-C", "1", "-i", "nobody", NOBODY],
+        "tests/expected/nobody.txt.insensitive.C1",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn context_invert() -> TestResult {
+    run(
+        &["-v", "-A", "1", "e", BUSTLE],
+        "tests/expected/bustle.txt.e.invert.A1",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn context_multiple_files() -> TestResult {
+    run(
+        &["-C", "1", "-B", "0", "-i", "the", BUSTLE, FOX, NOBODY],
+        "tests/expected/all.the.lowercase.insensitive.C1B0",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn count_ignores_context() -> TestResult {
+    run(
+        &["-c", "-C", "2", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized.count",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn line_numbers() -> TestResult {
+    run(&["-n", "the", BUSTLE], "tests/expected/bustle.txt.the.lowercase.n")
+}
+
+// --------------------------------------------------
+#[test]
+fn byte_offsets() -> TestResult {
+    run(
+        &["--byte-offset", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.lowercase.b",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn only_matching() -> TestResult {
+    run(
+        &["-obni", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.lowercase.insensitive.onb",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn line_numbers_context_multiple() -> TestResult {
+    run(
+        &["-n", "-C", "1", "Nobody", NOBODY, FOX],
+        "tests/expected/all.nobody.n.C1",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn with_filename() -> TestResult {
+    run(
+        &["-H", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized.H",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn no_filename() -> TestResult {
+    run(
+        &["-h", "The", BUSTLE, EMPTY, FOX, NOBODY],
+        "tests/expected/all.the.capitalized.h",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn color_never() -> TestResult {
+    run(
+        &["--color=never", "-H", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized.H",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn color_always() -> TestResult {
+    run_colors(
+        &["--color=always", "-n", "-C", "1", "-i", "the", BUSTLE, FOX],
+        "",
+        "tests/expected/all.the.lowercase.insensitive.color",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn color_grep_colors() -> TestResult {
+    run_colors(
+        &["--colour=always", "-H", "Nobody", NOBODY],
+        "mt=01;32:fn=34:sl=4:ne",
+        "tests/expected/nobody.txt.color.grep_colors",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn files_with_matches() -> TestResult {
+    run(
+        &["-l", "-i", "the", BUSTLE, EMPTY, FOX, NOBODY],
+        "tests/expected/all.the.lowercase.insensitive.l",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn files_without_match() -> TestResult {
+    run(
+        &["-L", "Nobody", BUSTLE, EMPTY, FOX, NOBODY],
+        "tests/expected/all.nobody.L",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn max_count_with_context() -> TestResult {
+    run(
+        &["-m", "1", "-A", "2", "-n", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.lowercase.m1A2",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn max_count_count() -> TestResult {
+    run(
+        &["-c", "-m", "1", "-i", "the", BUSTLE, FOX, NOBODY],
+        "tests/expected/all.the.lowercase.insensitive.m1.count",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_match() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-q", "fox", FOX])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_no_match() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--silent", "zebra", FOX])
+        .assert()
+        .code(1)
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_match_ignores_bad_file() -> TestResult {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["-q", "fox", &bad, FOX])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bad_file_status() -> TestResult {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["-l", "fox", FOX, &bad])
+        .assert()
+        .code(2)
+        .stdout(format!("{FOX}\n"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_count_zero() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-m", "0", "fox", FOX])
+        .assert()
+        .code(1)
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_patterns() -> TestResult {
+    run(
+        &["-e", "fox", "-e", "Nobody", FOX, NOBODY],
+        "tests/expected/all.fox.nobody.e",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn pattern_file() -> TestResult {
+    run(
+        &["-f", "tests/patterns.txt", BUSTLE, FOX, NOBODY],
+        "tests/expected/all.patterns.f",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_pattern_file() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = format!("{bad}: .* [(]os error 2[)]");
+    Command::cargo_bin(PRG)?
+        .args(["-f", &bad, FOX])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::is_match(expected)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_pattern_among_many() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-e", "fox", "-e", "*foo", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"Invalid pattern "*foo""#));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fixed_strings() -> TestResult {
+    run(
+        &["-F", "-o", "-e", ".", "-e", "The", BUSTLE],
+        "tests/expected/bustle.txt.fixed.o",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn word_regexp() -> TestResult {
+    run(
+        &["-w", "-i", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.insensitive.w",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn line_regexp() -> TestResult {
+    run(
+        &["-F", "-x", "Are you—Nobody—too?", NOBODY],
+        "tests/expected/nobody.txt.x",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn basic_regexp() -> TestResult {
+    run(
+        &["-G", "-o", r"\(No\|Some\)body", NOBODY],
+        "tests/expected/nobody.txt.basic.o",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn perl_regexp() -> TestResult {
+    run(
+        &["-P", "-o", r"N\w+(?=!)|\w+(?=—too)", NOBODY],
+        "tests/expected/nobody.txt.perl.o",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_perl_backtrack_limit() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-P", r"(a|a)*(?!a)b", "-"])
+        .write_stdin("a".repeat(30) + "\n")
+        .assert()
+        .code(2)
+        .stdout("")
+        .stderr(predicate::str::contains("-: Error executing regex"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_binary() -> TestResult {
+    run(&["-r", "fox", "tests/tree"], "tests/expected/tree.fox.recursive")
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_gitignore() -> TestResult {
+    run(
+        &["-r", "--gitignore", "fox", "tests/tree"],
+        "tests/expected/tree.fox.gitignore",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_include_exclude_dir() -> TestResult {
+    run(
+        &["-r", "--include", "*.txt", "--exclude-dir", "build", "fox", "tests/tree"],
+        "tests/expected/tree.fox.include.exclude-dir",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_exclude() -> TestResult {
+    run(
+        &["-rl", "--exclude", "*.txt", "--exclude", ".*", "fox", "tests/tree"],
+        "tests/expected/tree.fox.exclude.l",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_as_text() -> TestResult {
+    run(&["-a", "fox", "tests/tree/data.bin"], "tests/expected/data.bin.fox.a")
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_count() -> TestResult {
+    run(&["-c", "fox", "tests/tree/data.bin"], "tests/expected/data.bin.fox.count")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_glob() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--include", "[", "fox", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unclosed character class"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn search_zip_recursive() -> TestResult {
+    run(&["-rz", "ERROR", "tests/zip"], "tests/expected/zip.error.recursive")
+}
+
+// --------------------------------------------------
+#[test]
+fn search_zip_count() -> TestResult {
+    run(
+        &[
+            "--search-zip",
+            "-c",
+            "INFO",
+            "tests/zip/app.log.1.gz",
+            "tests/zip/app.log.2.bz2",
+            "tests/zip/app.log.3.xz",
+            "tests/zip/app.log.4.zst",
+        ],
+        "tests/expected/zip.info.count",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn search_zip_stdin_in_pieces() -> TestResult {
+    use std::io::Write;
+    use std::process::Stdio;
+    use std::time::Duration;
+
+    // The magic bytes arrive split across two reads of the pipe
+    let compressed = fs::read("tests/zip/app.log.3.xz")?;
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(["--search-zip", "-c", "INFO", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(&compressed[..2])?;
+    stdin.flush()?;
+    std::thread::sleep(Duration::from_millis(200));
+    stdin.write_all(&compressed[2..])?;
+    drop(stdin);
+    let output = child.wait_with_output()?;
+    assert_eq!(String::from_utf8(output.stdout)?, "1\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn json() -> TestResult {
+    run_json(
+        &["--json", "-B1", "-i", "the", BUSTLE, EMPTY, FOX],
+        "tests/expected/all.the.insensitive.B1.json",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn json_binary() -> TestResult {
+    run_json(&["--json", "fox", "tests/tree/data.bin"], "tests/expected/data.bin.fox.json")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_json_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--json", "-c", "fox", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn replace() -> TestResult {
+    run(
+        &["--replace", "$1-$1", "-E", "(fox|dog)", FOX],
+        "tests/expected/fox.txt.replace",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn replace_insensitive_line_number() -> TestResult {
+    run(
+        &["-n", "-i", "--replace", "<$0>", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.insensitive.n.replace",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn replace_named_only_matching() -> TestResult {
+    run(
+        &["-o", "-b", "--replace", "[${w}]", "-P", r"(?<w>\w+)(?=ody)", NOBODY],
+        "tests/expected/nobody.txt.perl.o.replace",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn in_place() -> TestResult {
+    let dir = std::env::temp_dir().join(format!("grepr-in-place-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let file = dir.join("bustle.txt");
+    fs::copy(BUSTLE, &file)?;
+
+    Command::cargo_bin(PRG)?
+        .args(["--in-place", "--replace", "${1}er", "-E", r"(\w+)est"])
+        .arg(&file)
+        .assert()
+        .success()
+        .stdout("");
+    let expected = fs::read_to_string("tests/expected/bustle.txt.in-place")?;
+    assert_eq!(fs::read_to_string(&file)?, expected);
+    assert_eq!(fs::read_dir(&dir)?.count(), 1);
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn in_place_dry_run() -> TestResult {
+    run(
+        &["--in-place", "--dry-run", "--replace", "${1}er", "-E", r"(\w+)est|(the)", BUSTLE, FOX],
+        "tests/expected/all.in-place.dry-run",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_in_place_without_replace() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--in-place", "fox", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--replace"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn streams_stdin() -> TestResult {
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    // The first line is printed while standard input is still open
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .arg("foo")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"foo\nbar\n")?;
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        let _ = stdout.read_line(&mut line);
+        let _ = tx.send(line);
+    });
+    let line = rx.recv_timeout(Duration::from_secs(10));
+    drop(stdin);
+    child.wait()?;
+    assert_eq!(line?, "foo\n");
+    Ok(())
+}
@@ -0,0 +1,3 @@
+The bustle in a house
+The morning after death
+The sweeping up the heart,
@@ -0,0 +1 @@
+97:The sweeping up the heart,
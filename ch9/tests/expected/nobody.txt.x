@@ -0,0 +1 @@
+Are you—Nobody—too?